@@ -2,48 +2,159 @@ use crate::token::{Token::{self, *}, Value};
 use crate::scanner::LocToken;
 use std::fmt::{Display, format, Formatter};
 use crate::icps::Error;
+use crate::parser::Assoc;
 
+#[derive(Clone)]
 pub enum Expr {
     Assign(LocToken, Box<Expr>),
     Unary(LocToken, Box<Expr>),
     Binary(Box<Expr>, LocToken, Box<Expr>),
-    Call(Box<Expr>, Vec<Expr>),
+    // a < b <= c, each operand evaluated once and the chain stopping at the first comparison that fails
+    Comparison(Box<Expr>, Vec<(LocToken, Expr)>),
+    Call(Box<Expr>, LocToken, Vec<Expr>, Vec<(LocToken, Expr)>),
     Get(Box<Expr>, LocToken),
+    // object?.name, null when the object is null
+    SafeGet(Box<Expr>, LocToken),
     Set(Box<Expr>, LocToken, Box<Expr>),
     Grouping(Box<Expr>),
+    Index(Box<Expr>, LocToken, Box<Expr>),
+    Lambda(LocToken, Vec<Param>, Option<LocToken>, Vec<Stmt>),
+    List(LocToken, Vec<Expr>),
+    Map(LocToken, Vec<(LocToken, Expr)>),
+    // [item for x in xs if cond] with its clauses as a generator body, collected into a list, or into a map when the bracket is '{' and it yields (key, value)
+    Comprehension(LocToken, Vec<Stmt>),
+    Tuple(LocToken, Vec<Expr>),
     Literal(LocToken),
     Logical(Box<Expr>, LocToken, Box<Expr>),
-    Super(LocToken),
+    // condition ? then : else, with the '?' token
+    Ternary(Box<Expr>, LocToken, Box<Expr>, Box<Expr>),
+    Await(LocToken, Box<Expr>),
+    Match(LocToken, Box<Expr>, Vec<(Case, Expr)>),
+    // super.method, with the keyword first
+    Super(LocToken, LocToken),
     This(LocToken),
     Variable(LocToken),
 }
 
+// the left-hand side of a declaration or assignment, every name it binds is a LocToken in source order
+#[derive(Clone)]
+pub enum Pattern {
+    Name(LocToken, Option<LocToken>),
+    List(LocToken, Vec<Pattern>),
+    Map(LocToken, Vec<(LocToken, Pattern)>),
+}
+
+impl Pattern {
+    pub fn token(&self) -> &LocToken {
+        match self {
+            Pattern::Name(token, _) | Pattern::List(token, _) | Pattern::Map(token, _) => token,
+        }
+    }
+
+    pub fn names(&self) -> Vec<&LocToken> {
+        match self {
+            Pattern::Name(name, _) => vec![name],
+            Pattern::List(_, items) => items.iter().flat_map(Pattern::names).collect(),
+            Pattern::Map(_, entries) => entries.iter().flat_map(|(_, p)| p.names()).collect(),
+        }
+    }
+}
+
+// one arm of a match, a bare name is a fieldless variant if one is in scope and binds the value otherwise
+#[derive(Clone)]
+pub enum Case {
+    Wildcard(LocToken),
+    Literal(LocToken),
+    Name(LocToken),
+    Variant(LocToken, Vec<Case>),
+}
+
+impl Case {
+    pub fn token(&self) -> &LocToken {
+        match self {
+            Case::Wildcard(token) | Case::Literal(token) | Case::Name(token) | Case::Variant(token, _) => token,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Param {
+    pub name: LocToken,
+    pub annotation: Option<LocToken>,
+    pub default: Option<Expr>,
+    pub variadic: bool,
+}
+
+#[derive(Clone)]
 pub enum Stmt {
     Block(Vec<Stmt>),
-    Class(LocToken, Box<Expr>, Vec<Box<Stmt>>),
+    // name, optional superclass, implemented traits, and the methods as Stmt::Function
+    Class(LocToken, Option<Expr>, Vec<Expr>, Vec<Stmt>),
+    // name, the methods implementors must provide, and the ones with default bodies as Stmt::Function
+    Trait(LocToken, Vec<(LocToken, Vec<Param>)>, Vec<Stmt>),
     Expression(Box<Expr>),
-    Function(LocToken, Vec<LocToken>, Vec<Box<Stmt>>),
+    // the flag marks an async fn
+    Function(LocToken, Vec<Param>, Option<LocToken>, Vec<Stmt>, bool),
     If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
     Log(Box<Expr>),
-    Return(Option<Expr>),
-    Declaration(LocToken, Option<Box<Expr>>),
+    Return(LocToken, Option<Expr>),
+    Yield(LocToken, Box<Expr>),
+    // the keyword is 'var', or 'let' / 'const' for bindings that cannot be reassigned
+    Declaration(LocToken, Vec<Pattern>, Vec<Expr>),
+    Assign(Vec<Pattern>, LocToken, Vec<Expr>),
     While(Box<Expr>, Box<Stmt>),
     For(Option<LocToken>, Box<Expr>, Box<Stmt>),
+    Infix(LocToken, u8, Assoc),
+    // name and each variant with its field names
+    Enum(LocToken, Vec<(LocToken, Vec<LocToken>)>),
+    Throw(LocToken, Box<Expr>),
+    Try(Vec<Stmt>, Option<(Option<LocToken>, Vec<Stmt>)>, Option<Vec<Stmt>>),
 }
 
 impl Display for Stmt {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", match self {
-            Stmt::Class(name, superclass, methods) => {
+            Stmt::Class(name, superclass, traits, methods) => {
                 let methods_str = methods.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
-                format!("class {} {} {}", name.token, superclass, methods_str)
+                let superclass_str = superclass.as_ref().map_or("".to_string(), |s| format!(" < {}", s));
+                let traits_str = if traits.is_empty() { "".to_string() } else { format!(" implements {}", list(traits)) };
+                format!("class {}{}{} [ {} ]", name.token, superclass_str, traits_str, methods_str)
+            }
+            Stmt::Trait(name, required, defaults) => {
+                let required_str = required.iter().map(|(method, params)| format!("fn {}({})", method.token, list(params))).collect::<Vec<_>>().join(", ");
+                format!("trait {} [ {} ] [ {} ]", name.token, required_str, list(defaults))
             }
             Stmt::Expression(expr) => format!("{}", expr),
             Stmt::If(condition, then, else_) => format!("if {} then {} else {}", condition, then, match else_ { Some(else_) => format!("{}", else_), None => "Nothing".to_string() }),
             Stmt::Log(expr) => format!("log {}", expr),
-            Stmt::Return(expr) => format!("return {}", expr.as_ref().map_or("".to_string(), ToString::to_string)),
-            Stmt::Declaration(name, initializer) => format!("var {} = {}", name.token, initializer.as_ref().map_or("".to_string(), ToString::to_string)),
+            Stmt::Return(_, expr) => format!("return {}", expr.as_ref().map_or("".to_string(), ToString::to_string)),
+            Stmt::Declaration(keyword, patterns, values) => format!("{} {} = {}", keyword.token, list(patterns), list(values)),
+            Stmt::Assign(patterns, _, values) => format!("{} = {}", list(patterns), list(values)),
             Stmt::While(condition, body) => format!("while {} {}", condition, body),
+            Stmt::Function(name, params, _, body, is_async) => {
+                let params_str = params.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+                let body_str = body.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+                format!("{}fn {}({}) [ {} ]", if *is_async { "async " } else { "" }, name.token, params_str, body_str)
+            }
+            Stmt::Throw(_, value) => format!("throw {}", value),
+            Stmt::Yield(_, value) => format!("yield {}", value),
+            Stmt::Try(body, catch, finally) => {
+                let catch_str = match catch {
+                    Some((name, handler)) => format!(" catch {} [ {} ]", name.as_ref().map_or("".to_string(), |n| n.token.to_string()), list(handler)),
+                    None => "".to_string()
+                };
+                let finally_str = finally.as_ref().map_or("".to_string(), |f| format!(" finally [ {} ]", list(f)));
+                format!("try [ {} ]{}{}", list(body), catch_str, finally_str)
+            }
+            Stmt::Infix(name, precedence, assoc) => format!("infix {} {} {:?}", name.token, precedence, assoc),
+            Stmt::Enum(name, variants) => {
+                let variants_str = variants.iter().map(|(variant, fields)| if fields.is_empty() {
+                    variant.token.to_string()
+                } else {
+                    format!("{}({})", variant.token, fields.iter().map(|f| f.token.to_string()).collect::<Vec<_>>().join(", "))
+                }).collect::<Vec<_>>().join(", ");
+                format!("enum {} {{ {} }}", name.token, variants_str)
+            }
             Stmt::Block(stmts) => {
                 let stmts_str = stmts.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
                 format!("block [ {} ]", stmts_str)
@@ -59,18 +170,113 @@ impl Display for Expr {
             Expr::Assign(name, value) => format!("{} = {}", name.token, value),
             Expr::Unary(operator, right) => format!("{} {}", operator.token, right),
             Expr::Binary(left, operator, right) => format!("{} {} {}", operator.token, left, right),
-            Expr::Call(callee, args) => {
-                let args_str = args.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+            Expr::Comparison(first, rest) => format!("{} {}", first, rest.iter().map(|(operator, operand)| format!("{} {}", operator.token, operand)).collect::<Vec<_>>().join(" ")),
+            Expr::Call(callee, _, args, named) => {
+                let args_str = args.iter().map(ToString::to_string)
+                    .chain(named.iter().map(|(name, value)| format!("{}: {}", name.token, value)))
+                    .collect::<Vec<_>>().join(", ");
                 format!("Call {} ({})", callee, args_str)
             }
             Expr::Get(object, name) => format!("{}.{}", object, name.token),
+            Expr::SafeGet(object, name) => format!("{}?.{}", object, name.token),
             Expr::Set(object, name, value) => format!("{}.{} = {}", object, name.token, value),
             Expr::Grouping(expr) => format!("grouping {}", expr),
+            Expr::Index(object, _, index) => format!("{}[{}]", object, index),
+            Expr::Lambda(_, params, _, body) => {
+                let params_str = params.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+                let body_str = body.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+                format!("fn ({}) [ {} ]", params_str, body_str)
+            }
+            Expr::List(_, items) => format!("[{}]", list(items)),
+            Expr::Comprehension(bracket, body) => format!("{} {} {}", bracket.token, list(body), if bracket.token == LeftBrace { "}" } else { "]" }),
+            Expr::Tuple(_, items) => format!("({})", list(items)),
+            Expr::Map(_, entries) => format!("{{{}}}", entries.iter().map(|(key, value)| format!("{}: {}", key.token, value)).collect::<Vec<_>>().join(", ")),
             Expr::Literal(value) => format!("{}", value.token),
             Expr::Logical(left, operator, right) => format!("{} {} {}", operator.token, left, right),
-            Expr::Super(keyword) => format!("super.{}", keyword.token),
-            Expr::This(keyword) => format!("this.{}", keyword.token),
+            Expr::Ternary(condition, _, then_value, else_value) => format!("{} ? {} : {}", condition, then_value, else_value),
+            Expr::Await(_, value) => format!("await {}", value),
+            Expr::Match(_, subject, arms) => format!("match {} {{ {} }}", subject, arms.iter().map(|(case, body)| format!("{}: {}", case, body)).collect::<Vec<_>>().join(", ")),
+            Expr::Super(_, method) => format!("super.{}", method.token),
+            Expr::This(_) => "this".to_string(),
             Expr::Variable(name) => format!("{}", name.token),
         })
     }
-}
\ No newline at end of file
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.variadic {
+            return write!(f, "...{}", self.name.token);
+        }
+        write!(f, "{}", self.name.token)?;
+        if let Some(annotation) = &self.annotation {
+            write!(f, ": {}", annotation.token)?;
+        }
+        if self.default.is_some() {
+            write!(f, " = ...")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Name(name, None) => write!(f, "{}", name.token),
+            Pattern::Name(name, Some(annotation)) => write!(f, "{}: {}", name.token, annotation.token),
+            Pattern::List(_, items) => write!(f, "[{}]", list(items)),
+            Pattern::Map(_, entries) => write!(f, "{{{}}}", entries.iter().map(|(key, p)| format!("{}: {}", key.token, p)).collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+impl Display for Case {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Case::Variant(name, fields) => write!(f, "{}({})", name.token, list(fields)),
+            case => write!(f, "{}", case.token().token),
+        }
+    }
+}
+
+// whether any of the statements yields, which makes the function they are the body of a generator
+pub fn yields(stmts: &[Stmt]) -> bool {
+    contains(stmts, &|stmt| matches!(stmt, Stmt::Yield(_, _)))
+}
+
+// whether running the statements can stop partway, at a yield or an await
+pub fn suspends(stmts: &[Stmt]) -> bool {
+    contains(stmts, &|stmt| matches!(stmt, Stmt::Yield(_, _)) || awaited(stmt).is_some())
+}
+
+fn contains(stmts: &[Stmt], found: &dyn std::ops::Fn(&Stmt) -> bool) -> bool {
+    stmts.iter().any(|stmt| found(stmt) || match stmt {
+        Stmt::Block(stmts) => contains(stmts, found),
+        Stmt::If(_, then_branch, else_branch) => contains(std::slice::from_ref(then_branch), found) || else_branch.as_ref().is_some_and(|e| contains(std::slice::from_ref(e), found)),
+        Stmt::While(_, body) | Stmt::For(_, _, body) => contains(std::slice::from_ref(body), found),
+        Stmt::Try(body, catch, finally) => contains(body, found) || catch.as_ref().is_some_and(|(_, handler)| contains(handler, found)) || finally.as_ref().is_some_and(|f| contains(f, found)),
+        _ => false,
+    })
+}
+
+// an async function can only suspend at an await that starts a statement, an initializer, an assignment or a return value
+pub fn awaited(stmt: &Stmt) -> Option<&Expr> {
+    let expr = match stmt {
+        Stmt::Expression(expr) => match &**expr {
+            Expr::Assign(_, value) | Expr::Set(_, _, value) => &**value,
+            expr => expr,
+        },
+        Stmt::Log(expr) => &**expr,
+        Stmt::Return(_, Some(expr)) => expr,
+        Stmt::Declaration(_, _, values) | Stmt::Assign(_, _, values) if values.len() == 1 => &values[0],
+        _ => return None,
+    };
+    match expr {
+        Expr::Await(_, operand) => Some(operand),
+        _ => None,
+    }
+}
+
+fn list<T: Display>(items: &[T]) -> std::string::String {
+    items.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,207 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::RangeInclusive;
+use crate::environment::Environment;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::generator::{self, Iter};
+use crate::icps::{self, Error};
+use crate::interpreter::Interpreter;
+use crate::scanner::LocToken;
+use crate::token::Value;
+
+type NativeFn = fn(&mut Interpreter, &LocToken, Vec<Value>) -> Result<Value, Error>;
+
+#[derive(Clone)]
+pub struct Native {
+    pub name: &'static str,
+    // how many arguments it takes, the ones past the start of the range are optional
+    pub arity: RangeInclusive<usize>,
+    pub func: NativeFn,
+}
+
+impl Debug for Native {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+pub fn define(env: &mut Environment) {
+    let natives = [
+        Native { name: "hex", arity: 1..=1, func: hex },
+        Native { name: "bin", arity: 1..=1, func: bin },
+        Native { name: "len", arity: 1..=1, func: len },
+        Native { name: "next", arity: 1..=1, func: next },
+        Native { name: "sleep", arity: 1..=1, func: sleep },
+        Native { name: "spawn", arity: 1..=1, func: spawn },
+        Native { name: "now", arity: 0..=0, func: now },
+        Native { name: "map", arity: 2..=2, func: map },
+        Native { name: "filter", arity: 2..=2, func: filter },
+        Native { name: "type", arity: 1..=1, func: type_of },
+        Native { name: "fields", arity: 1..=1, func: fields },
+        Native { name: "globals", arity: 0..=0, func: globals },
+        Native { name: "locals", arity: 0..=0, func: locals },
+        Native { name: "defined", arity: 1..=1, func: defined },
+        Native { name: "eval", arity: 1..=2, func: eval },
+    ];
+    for native in natives {
+        env.values.insert(native.name.to_string(), Value::Native(native));
+    }
+}
+
+pub fn integer(value: &Value, loc: &LocToken) -> Result<i64, Error> {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n <= i64::MAX as f64 => Ok(*n as i64),
+        Value::Number(n) => Err(Error::new(loc.loc, format!("Runtime Error: Expected an integer, but found '{}'.", n).as_str())),
+        _ => Err(Error::new(loc.loc, "Runtime Error: Expected an integer 'Number' or an expression evaluating to it"))
+    }
+}
+
+fn hex(_: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    let n = integer(&args[0], paren)?;
+    let sign = if n < 0 { "-" } else { "" };
+    Ok(Value::String(format!("{}0x{:x}", sign, n.unsigned_abs())))
+}
+
+fn bin(_: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    let n = integer(&args[0], paren)?;
+    let sign = if n < 0 { "-" } else { "" };
+    Ok(Value::String(format!("{}0b{:b}", sign, n.unsigned_abs())))
+}
+
+fn len(_: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    match &args[0] {
+        Value::List(items) => Ok(Value::Number(items.borrow().len() as f64)),
+        Value::Map(entries) => Ok(Value::Number(entries.borrow().len() as f64)),
+        Value::Tuple(items) => Ok(Value::Number(items.len() as f64)),
+        Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+        _ => Err(Error::new(paren.loc, "Runtime Error: Can only take the length of a 'List', 'Tuple', 'Map' or 'String'."))
+    }
+}
+
+// the generator's next value, or null once it has finished
+fn next(interpreter: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Generator(g) => Ok(generator::resume(g, interpreter, paren)?.unwrap_or(Value::Null)),
+        _ => Err(Error::new(paren.loc, "Runtime Error: Can only call 'next' on a generator."))
+    }
+}
+
+// map(xs, f) and filter(xs, f) walk anything a for loop can and collect into a list, so they read left to right after |>
+fn map(interpreter: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    let mut iter = Iter::new(args[0].clone(), paren)?;
+    let mut items = Vec::new();
+    while let Some(item) = iter.next(interpreter)? {
+        items.push(interpreter.call(args[1].clone(), paren, vec![item])?);
+    }
+    Ok(Value::List(Rc::new(RefCell::new(items))))
+}
+
+fn filter(interpreter: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    let mut iter = Iter::new(args[0].clone(), paren)?;
+    let mut items = Vec::new();
+    while let Some(item) = iter.next(interpreter)? {
+        let keep = interpreter.call(args[1].clone(), paren, vec![item.clone()])?;
+        if interpreter.truthy(&keep, paren.loc)? {
+            items.push(item);
+        }
+    }
+    Ok(Value::List(Rc::new(RefCell::new(items))))
+}
+
+// a task that finishes after the given number of milliseconds
+fn sleep(interpreter: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    match args[0] {
+        Value::Number(ms) if ms >= 0.0 => Ok(Value::Task(interpreter.scheduler.sleep(ms))),
+        _ => Err(Error::new(paren.loc, "Runtime Error: Can only sleep for a non-negative 'Number' of milliseconds."))
+    }
+}
+
+// calls f with no arguments as its own task, without waiting for it
+fn spawn(interpreter: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    match &args[0] {
+        f @ (Value::Function(_) | Value::Native(_)) => Ok(Value::Task(interpreter.scheduler.spawn(f.clone(), paren))),
+        _ => Err(Error::new(paren.loc, "Runtime Error: Can only spawn a function."))
+    }
+}
+
+fn now(interpreter: &mut Interpreter, _: &LocToken, _: Vec<Value>) -> Result<Value, Error> {
+    Ok(Value::Number(interpreter.scheduler.now()))
+}
+
+// instances and variants are named after their class and enum, everything else after its kind
+fn type_of(_: &mut Interpreter, _: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    Ok(Value::String(type_name(&args[0])))
+}
+
+// the class name for instances and the enum name for variants, so errors can name user types
+pub fn type_name(value: &Value) -> std::string::String {
+    match value {
+        Value::Instance(instance) => instance.borrow().class.name.token.to_string(),
+        Value::Variant(variant) => variant.ty.name.token.to_string(),
+        value => value.type_name().to_string(),
+    }
+}
+
+// the sorted names get_field can find on the value, fields before methods for instances
+fn fields(_: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    let names = match &args[0] {
+        Value::Instance(instance) => {
+            let instance = instance.borrow();
+            let mut names: Vec<String> = instance.fields.keys().cloned().collect();
+            names.sort();
+            names.extend(instance.class.method_names().into_iter().filter(|name| !instance.fields.contains_key(name)));
+            names
+        }
+        Value::Class(class) => class.method_names(),
+        Value::Map(entries) => entries.borrow().keys().cloned().collect(),
+        Value::Variant(variant) => variant.ty.variants[variant.tag].1.clone(),
+        Value::Enum(ty) => ty.variants.iter().map(|(name, _)| name.clone()).collect(),
+        value => return Err(Error::new(paren.loc, format!("Runtime Error: '{}' has no fields.", value.type_name()).as_str()))
+    };
+    Ok(Value::List(Rc::new(RefCell::new(names.into_iter().map(Value::String).collect()))))
+}
+
+fn globals(interpreter: &mut Interpreter, _: &LocToken, _: Vec<Value>) -> Result<Value, Error> {
+    Ok(Value::Map(Rc::new(RefCell::new(interpreter.env.borrow().globals()))))
+}
+
+fn locals(interpreter: &mut Interpreter, _: &LocToken, _: Vec<Value>) -> Result<Value, Error> {
+    Ok(Value::Map(Rc::new(RefCell::new(interpreter.env.borrow().locals()))))
+}
+
+fn defined(interpreter: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    match &args[0] {
+        Value::String(name) => Ok(Value::Boolean(interpreter.env.borrow().defined(name))),
+        _ => Err(Error::new(paren.loc, "Runtime Error: Can only check whether a 'String' name is defined."))
+    }
+}
+
+// eval(source) runs in the caller's scope, eval(source, true) in a fresh one holding only these natives,
+// errors keep their place in the source and say where eval was called, while thrown values pass through to the caller's try
+fn eval(interpreter: &mut Interpreter, paren: &LocToken, args: Vec<Value>) -> Result<Value, Error> {
+    let source = match &args[0] {
+        Value::String(source) => source.clone(),
+        _ => return Err(Error::new(paren.loc, "Runtime Error: Can only eval a 'String'."))
+    };
+    let isolated = match args.get(1) {
+        Some(value) => interpreter.truthy(value, paren.loc)?,
+        None => false
+    };
+    let env = if isolated {
+        let mut env = Environment::new();
+        define(&mut env);
+        Rc::new(RefCell::new(env))
+    } else {
+        interpreter.env.clone()
+    };
+    icps::eval(&source, interpreter, env, isolated).map_err(|e| match e.unwind {
+        Some(_) => e,
+        None => Error::new(e.loc(), format!("{}\n    in eval called from [{}:{}]", e.message(), paren.loc.line, paren.loc.col).as_str())
+    })
+}
@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use crate::ast::{Case, Expr, Param, Pattern, Stmt};
+use crate::icps::{self, Error};
+use crate::interpreter::Interpreter;
+use crate::scanner::LocToken;
+use crate::token::Token;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Any,
+    Null,
+    Number,
+    String,
+    Boolean,
+    Range,
+    List,
+    Map,
+    Tuple,
+    Error,
+    Function(Option<Rc<Signature>>),
+    // the enum and its variants and constructors share this type, so a match can see which variants exist
+    Enum(Rc<EnumType>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EnumType {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Signature {
+    pub params: Vec<(String, Type)>,
+    pub returns: Type,
+}
+
+// unannotated variables are 'Any', so code without annotations is only rejected where it would fail whatever values flow in
+#[derive(Clone, Debug)]
+pub struct Binding {
+    pub ty: Type,
+    pub annotated: bool,
+}
+
+pub type Scope = HashMap<String, Binding>;
+
+pub struct Checker<'a> {
+    globals: &'a mut Scope,
+    scopes: Vec<Scope>,
+    returns: Vec<Option<Type>>,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Type::Any => "Any",
+            Type::Null => "Null",
+            Type::Number => "Number",
+            Type::String => "String",
+            Type::Boolean => "Boolean",
+            Type::Range => "Range",
+            Type::List => "List",
+            Type::Map => "Map",
+            Type::Tuple => "Tuple",
+            Type::Error => "Error",
+            Type::Function(_) => "Function",
+            Type::Enum(ty) => ty.name.as_str(),
+        })
+    }
+}
+
+impl Type {
+    fn from_annotation(token: &LocToken) -> Result<Type, Error> {
+        match token.token.to_string().as_str() {
+            "Any" => Ok(Type::Any),
+            "Null" => Ok(Type::Null),
+            "Number" => Ok(Type::Number),
+            "String" => Ok(Type::String),
+            "Boolean" => Ok(Type::Boolean),
+            "Range" => Ok(Type::Range),
+            "List" => Ok(Type::List),
+            "Map" => Ok(Type::Map),
+            "Tuple" => Ok(Type::Tuple),
+            "Error" => Ok(Type::Error),
+            "Function" => Ok(Type::Function(None)),
+            name => Err(Error::new(token.loc, format!("Type Error: Unknown type '{}'.", name).as_str()))
+        }
+    }
+
+    pub fn accepts(&self, actual: &Type) -> bool {
+        match (self, actual) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Function(_), Type::Function(_)) => true,
+            (expected, actual) => expected == actual
+        }
+    }
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(globals: &'a mut Scope, max_depth: usize) -> Self {
+        Checker { globals, scopes: Vec::new(), returns: Vec::new(), depth: 0, max_depth }
+    }
+
+    pub fn check(&mut self, stmts: &[Stmt]) -> Result<(), Error> {
+        for stmt in stmts {
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn declare(&mut self, name: &LocToken, binding: Binding) {
+        match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.token.to_string(), binding),
+            None => self.globals.insert(name.token.to_string(), binding),
+        };
+    }
+
+    fn lookup(&mut self, name: &LocToken) -> Option<&mut Binding> {
+        let name = name.token.to_string();
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(binding) = scope.get_mut(&name) {
+                return Some(binding);
+            }
+        }
+        self.globals.get_mut(&name)
+    }
+
+    fn block(&mut self, stmts: &[Stmt], bindings: Scope) -> Result<(), Error> {
+        self.scopes.push(bindings);
+        let out = self.check(stmts);
+        self.scopes.pop();
+        out
+    }
+
+    fn signature(params: &[Param], returns: &Option<LocToken>) -> Result<Rc<Signature>, Error> {
+        let mut types = Vec::new();
+        for param in params.iter().filter(|p| !p.variadic) {
+            let ty = match &param.annotation {
+                Some(annotation) => Type::from_annotation(annotation)?,
+                None => Type::Any
+            };
+            types.push((param.name.token.to_string(), ty));
+        }
+        let returns = match returns {
+            Some(annotation) => Type::from_annotation(annotation)?,
+            None => Type::Any
+        };
+        Ok(Rc::new(Signature { params: types, returns }))
+    }
+
+    fn function(&mut self, params: &[Param], returns: &Option<LocToken>, body: &[Stmt], signature: &Signature) -> Result<(), Error> {
+        let mut bindings = Scope::new();
+        for param in params {
+            if let Some(default) = &param.default {
+                let actual = self.expr(default)?;
+                let expected = Self::param_type(signature, param);
+                if !expected.accepts(&actual) {
+                    return Err(Error::new(param.name.loc, format!("Type Error: Default of parameter '{}' declared as '{}' is a '{}'.", param.name.token, expected, actual).as_str()));
+                }
+            }
+            let ty = if param.variadic { Type::List } else { Self::param_type(signature, param) };
+            bindings.insert(param.name.token.to_string(), Binding { ty, annotated: param.annotation.is_some() });
+        }
+        self.returns.push(returns.as_ref().map(|_| signature.returns.clone()));
+        let out = self.block(body, bindings);
+        self.returns.pop();
+        out
+    }
+
+    fn param_type(signature: &Signature, param: &Param) -> Type {
+        signature.params.iter()
+            .find(|(name, _)| *name == param.name.token.to_string())
+            .map_or(Type::Any, |(_, ty)| ty.clone())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), Error> {
+        match stmt {
+            Stmt::Expression(e) | Stmt::Log(e) | Stmt::Throw(_, e) | Stmt::Yield(_, e) => {
+                self.expr(e)?;
+            }
+
+            Stmt::Declaration(_, patterns, values) => {
+                let mut types = Vec::new();
+                for value in values {
+                    types.push(self.expr(value)?);
+                }
+                for (i, pattern) in patterns.iter().enumerate() {
+                    let ty = if types.len() == patterns.len() { types[i].clone() } else { Type::Any };
+                    self.declare_pattern(pattern, ty, values.is_empty())?;
+                }
+            }
+
+            Stmt::Assign(patterns, _, values) => {
+                let mut types = Vec::new();
+                for value in values {
+                    types.push(self.expr(value)?);
+                }
+                for (i, pattern) in patterns.iter().enumerate() {
+                    let ty = if types.len() == patterns.len() { types[i].clone() } else { Type::Any };
+                    match pattern {
+                        Pattern::Name(name, _) => self.assign(name, &ty)?,
+                        pattern => for name in pattern.names() {
+                            self.assign(name, &Type::Any)?;
+                        }
+                    }
+                }
+            }
+
+            Stmt::Function(name, params, returns, body, _) => {
+                let signature = Self::signature(params, returns)?;
+                self.declare(name, Binding { ty: Type::Function(Some(signature.clone())), annotated: false });
+                self.function(params, returns, body, &signature)?;
+            }
+
+            Stmt::Return(keyword, value) => {
+                let actual = match value {
+                    Some(v) => self.expr(v)?,
+                    None => Type::Null
+                };
+                if let Some(Some(expected)) = self.returns.last() {
+                    if !expected.accepts(&actual) {
+                        return Err(Error::new(keyword.loc, format!("Type Error: Cannot return a '{}' from a function declared to return '{}'.", actual, expected).as_str()));
+                    }
+                }
+            }
+
+            Stmt::Block(stmts) => self.block(stmts, Scope::new())?,
+
+            Stmt::If(condition, then_branch, else_branch) => {
+                self.expr(condition)?;
+                self.statement(then_branch)?;
+                if let Some(e) = else_branch {
+                    self.statement(e)?;
+                }
+            }
+
+            Stmt::While(condition, body) => {
+                self.expr(condition)?;
+                self.statement(body)?;
+            }
+
+            // only a range is known to give numbers, lists, strings, maps and generators can give anything
+            Stmt::For(name, iterable, body) => {
+                let ty = if self.expr(iterable)? == Type::Range { Type::Number } else { Type::Any };
+                let mut bindings = Scope::new();
+                bindings.insert(name.as_ref().map_or("i".to_string(), |n| n.token.to_string()), Binding { ty, annotated: false });
+                self.scopes.push(bindings);
+                let out = self.statement(body);
+                self.scopes.pop();
+                out?;
+            }
+
+            Stmt::Try(body, catch, finally) => {
+                self.block(body, Scope::new())?;
+                if let Some((name, handler)) = catch {
+                    let mut bindings = Scope::new();
+                    if let Some(name) = name {
+                        bindings.insert(name.token.to_string(), Binding { ty: Type::Any, annotated: false });
+                    }
+                    self.block(handler, bindings)?;
+                }
+                if let Some(finally) = finally {
+                    self.block(finally, Scope::new())?;
+                }
+            }
+
+            Stmt::Class(name, superclass, traits, methods) => {
+                if let Some(superclass) = superclass {
+                    self.expr(superclass)?;
+                }
+                for t in traits {
+                    self.expr(t)?;
+                }
+                self.declare(name, Binding { ty: Type::Any, annotated: false });
+                for method in methods {
+                    if let Stmt::Function(_, params, returns, body, _) = method {
+                        let signature = Self::signature(params, returns)?;
+                        self.function(params, returns, body, &signature)?;
+                    }
+                }
+            }
+
+            Stmt::Trait(name, _, defaults) => {
+                self.declare(name, Binding { ty: Type::Any, annotated: false });
+                for method in defaults {
+                    if let Stmt::Function(_, params, returns, body, _) = method {
+                        let signature = Self::signature(params, returns)?;
+                        self.function(params, returns, body, &signature)?;
+                    }
+                }
+            }
+
+            Stmt::Enum(name, variants) => {
+                let ty = Type::Enum(Rc::new(EnumType {
+                    name: name.token.to_string(),
+                    variants: variants.iter().map(|(variant, _)| variant.token.to_string()).collect(),
+                }));
+                self.declare(name, Binding { ty: ty.clone(), annotated: false });
+                for (variant, _) in variants {
+                    self.declare(variant, Binding { ty: ty.clone(), annotated: false });
+                }
+            }
+
+            Stmt::Infix(_, _, _) => {}
+        }
+        Ok(())
+    }
+
+    fn declare_pattern(&mut self, pattern: &Pattern, actual: Type, uninitialized: bool) -> Result<(), Error> {
+        match pattern {
+            Pattern::Name(name, Some(annotation)) => {
+                let expected = Type::from_annotation(annotation)?;
+                if !uninitialized && !expected.accepts(&actual) {
+                    return Err(Error::new(name.loc, format!("Type Error: Cannot initialize '{}' declared as '{}' with a '{}'.", name.token, expected, actual).as_str()));
+                }
+                self.declare(name, Binding { ty: expected, annotated: true });
+            }
+            pattern => for name in pattern.names() {
+                self.declare(name, Binding { ty: Type::Any, annotated: false });
+            }
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, name: &LocToken, actual: &Type) -> Result<(), Error> {
+        if let Some(binding) = self.lookup(name) {
+            if !binding.annotated {
+                binding.ty = Type::Any;
+            } else if !binding.ty.accepts(actual) {
+                return Err(Error::new(name.loc, format!("Type Error: Cannot assign a '{}' to '{}' declared as '{}'.", actual, name.token, binding.ty).as_str()));
+            }
+        }
+        Ok(())
+    }
+
+    // left-associative chains nest without recursing in the parser, so the checker keeps its own count
+    fn expr(&mut self, expr: &Expr) -> Result<Type, Error> {
+        if self.depth >= self.max_depth {
+            let loc = Interpreter::get_loc_token_from_expr(expr).loc;
+            return Err(Error::new(loc, format!("Nesting went deeper than {}.", self.max_depth).as_str()));
+        }
+        self.depth += 1;
+        let ty = self.expr_type(expr);
+        self.depth -= 1;
+        ty
+    }
+
+    fn expr_type(&mut self, expr: &Expr) -> Result<Type, Error> {
+        Ok(match expr {
+            Expr::Literal(token) => match token.token {
+                Token::Number(_) => Type::Number,
+                Token::String(_) => Type::String,
+                Token::True | Token::False => Type::Boolean,
+                Token::Null => Type::Null,
+                _ => Type::Any
+            },
+
+            Expr::Grouping(e) => self.expr(e)?,
+
+            Expr::Variable(name) => self.lookup(name).map_or(Type::Any, |binding| binding.ty.clone()),
+
+            Expr::Assign(name, value) => {
+                let actual = self.expr(value)?;
+                self.assign(name, &actual)?;
+                actual
+            }
+
+            Expr::Unary(op, right) if op.token == Token::Not || op.token == Token::Bang => {
+                self.expr(right)?;
+                Type::Boolean
+            }
+
+            Expr::Unary(op, right) => {
+                let right_type = self.expr(right)?;
+                if !Type::Number.accepts(&right_type) {
+                    let error = Error::new(op.loc, format!("Type Error: Cannot apply '{}' to '{}'.", op.token, right_type).as_str());
+                    return self.mismatch(error, &[right]);
+                }
+                Type::Number
+            }
+
+            Expr::Binary(left, op, right) => {
+                let left_type = self.expr(left)?;
+                let right_type = self.expr(right)?;
+                match Self::binary(op, &left_type, &right_type) {
+                    Ok(ty) => ty,
+                    Err(error) => self.mismatch(error, &[left, right])?
+                }
+            }
+
+            Expr::Comparison(first, rest) => {
+                let mut left = first.as_ref();
+                let mut left_type = self.expr(left)?;
+                for (op, operand) in rest {
+                    let right_type = self.expr(operand)?;
+                    if let Err(error) = Self::binary(op, &left_type, &right_type) {
+                        self.mismatch(error, &[left, operand])?;
+                    }
+                    left = operand;
+                    left_type = right_type;
+                }
+                Type::Boolean
+            }
+
+            Expr::Logical(left, op, right) if op.token == Token::QuestionQuestion => {
+                let left = self.expr(left)?;
+                let right = self.expr(right)?;
+                match left {
+                    Type::Null => right,
+                    left if left == right => left,
+                    _ => Type::Any
+                }
+            }
+
+            Expr::Ternary(condition, _, then_value, else_value) => {
+                self.expr(condition)?;
+                let then_type = self.expr(then_value)?;
+                let else_type = self.expr(else_value)?;
+                if then_type == else_type { then_type } else { Type::Any }
+            }
+
+            Expr::Logical(left, _, right) => {
+                self.expr(left)?;
+                self.expr(right)?;
+                Type::Boolean
+            }
+
+            Expr::Call(callee, paren, args, named) => {
+                let callee = self.expr(callee)?;
+                let mut positional = Vec::new();
+                for arg in args {
+                    positional.push(self.expr(arg)?);
+                }
+                let mut by_name = Vec::new();
+                for (name, arg) in named {
+                    by_name.push((name, self.expr(arg)?));
+                }
+                match callee {
+                    Type::Function(Some(signature)) => {
+                        let named = by_name.into_iter().filter_map(|(name, actual)| {
+                            signature.params.iter().find(|(param, _)| *param == name.token.to_string()).map(|(param, expected)| (param, expected, actual))
+                        });
+                        for (param, expected, actual) in signature.params.iter().zip(positional).map(|((param, expected), actual)| (param, expected, actual)).chain(named) {
+                            if !expected.accepts(&actual) {
+                                return Err(Error::new(paren.loc, format!("Type Error: Argument '{}' declared as '{}' was given a '{}'.", param, expected, actual).as_str()));
+                            }
+                        }
+                        signature.returns.clone()
+                    }
+                    Type::Function(None) | Type::Any => Type::Any,
+                    Type::Enum(ty) => Type::Enum(ty),
+                    other => return Err(Error::new(paren.loc, format!("Type Error: Cannot call a '{}'.", other).as_str()))
+                }
+            }
+
+            Expr::Lambda(_, params, returns, body) => {
+                let signature = Self::signature(params, returns)?;
+                self.function(params, returns, body, &signature)?;
+                Type::Function(Some(signature))
+            }
+
+            Expr::Index(object, _, index) => {
+                self.expr(object)?;
+                self.expr(index)?;
+                Type::Any
+            }
+
+            Expr::List(_, items) => {
+                for item in items {
+                    self.expr(item)?;
+                }
+                Type::List
+            }
+
+            Expr::Comprehension(bracket, body) => {
+                self.block(body, Scope::new())?;
+                if bracket.token == Token::LeftBrace { Type::Map } else { Type::List }
+            }
+
+            Expr::Tuple(_, items) => {
+                for item in items {
+                    self.expr(item)?;
+                }
+                Type::Tuple
+            }
+
+            Expr::Map(_, entries) => {
+                for (_, value) in entries {
+                    self.expr(value)?;
+                }
+                Type::Map
+            }
+
+            Expr::Get(object, _) | Expr::SafeGet(object, _) => {
+                self.expr(object)?;
+                Type::Any
+            }
+
+            Expr::Set(object, _, value) => {
+                self.expr(object)?;
+                self.expr(value)?
+            }
+
+            Expr::Await(_, operand) => {
+                self.expr(operand)?;
+                Type::Any
+            }
+
+            Expr::Match(keyword, subject, arms) => {
+                self.expr(subject)?;
+                let mut result: Option<Type> = None;
+                let mut matched: Option<Rc<EnumType>> = None;
+                let mut covered = Vec::new();
+                let mut exhaustive = false;
+                for (case, body) in arms {
+                    let mut bindings = Scope::new();
+                    self.bind_case(case, &mut bindings);
+                    match case {
+                        Case::Variant(name, _) | Case::Name(name) if self.variant(name).is_some() => {
+                            matched = self.variant(name);
+                            let complete = match case {
+                                Case::Variant(_, fields) => fields.iter().all(|field| self.irrefutable(field)),
+                                _ => true
+                            };
+                            if complete {
+                                covered.push(name.token.to_string());
+                            }
+                        }
+                        case if self.irrefutable(case) => exhaustive = true,
+                        _ => {}
+                    }
+                    self.scopes.push(bindings);
+                    let ty = self.expr(body);
+                    self.scopes.pop();
+                    let ty = ty?;
+                    result = Some(match result {
+                        Some(previous) if previous != ty => Type::Any,
+                        _ => ty
+                    });
+                }
+                if let (false, Some(ty)) = (exhaustive, matched) {
+                    let missing: Vec<_> = ty.variants.iter().filter(|v| !covered.contains(v)).map(|v| format!("'{}'", v)).collect();
+                    if !missing.is_empty() {
+                        icps::warn(keyword.loc.line, keyword.loc.col, format!("Match over '{}' does not cover {}.", ty.name, missing.join(", ")).as_str());
+                    }
+                }
+                result.unwrap_or(Type::Any)
+            }
+
+            Expr::Super(_, _) | Expr::This(_) => Type::Any,
+        })
+    }
+
+    // the enum a name belongs to, when it names one of its variants
+    fn variant(&mut self, name: &LocToken) -> Option<Rc<EnumType>> {
+        match self.lookup(name) {
+            Some(Binding { ty: Type::Enum(ty), .. }) if ty.variants.contains(&name.token.to_string()) => Some(ty.clone()),
+            _ => None
+        }
+    }
+
+    fn irrefutable(&mut self, case: &Case) -> bool {
+        match case {
+            Case::Wildcard(_) => true,
+            Case::Name(name) => self.variant(name).is_none(),
+            Case::Literal(_) | Case::Variant(_, _) => false,
+        }
+    }
+
+    fn bind_case(&mut self, case: &Case, bindings: &mut Scope) {
+        match case {
+            Case::Name(name) if self.variant(name).is_none() => {
+                bindings.insert(name.token.to_string(), Binding { ty: Type::Any, annotated: false });
+            }
+            Case::Variant(_, fields) => for field in fields {
+                self.bind_case(field, bindings);
+            },
+            _ => {}
+        }
+    }
+
+    // the operand combinations the interpreter accepts for each operator, with the type it produces
+    // inferred types alone may sit in code that never runs, so their mismatches are only warned about and the script still starts
+    fn mismatch(&mut self, error: Error, operands: &[&Expr]) -> Result<Type, Error> {
+        if operands.iter().any(|operand| self.declared(operand)) {
+            return Err(error);
+        }
+        icps::warn(error.loc().line, error.loc().col, error.message().trim_start_matches("Type Error: "));
+        Ok(Type::Any)
+    }
+
+    // whether an operand's type rests on an annotated binding or a declared return type
+    fn declared(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Variable(name) | Expr::Assign(name, _) => self.lookup(name).is_some_and(|binding| binding.annotated),
+            Expr::Call(callee, _, _, _) => match callee.as_ref() {
+                Expr::Variable(name) => self.lookup(name).is_some_and(|binding| matches!(&binding.ty, Type::Function(Some(signature)) if signature.returns != Type::Any)),
+                _ => false
+            },
+            Expr::Grouping(e) | Expr::Unary(_, e) => self.declared(e),
+            Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => self.declared(left) || self.declared(right),
+            Expr::Ternary(_, _, then_value, else_value) => self.declared(then_value) || self.declared(else_value),
+            _ => false
+        }
+    }
+
+    fn binary(op: &LocToken, left: &Type, right: &Type) -> Result<Type, Error> {
+        use Type::{Boolean, List, Number, Range, String, Tuple};
+        let rules: &[(Type, Type, Type)] = match op.token {
+            Token::Plus => &[(Number, Number, Number), (Number, String, String), (String, Number, String), (String, String, String)],
+            Token::Star => &[(Number, Number, Number), (Number, String, String), (String, Number, String)],
+            Token::Minus | Token::Slash | Token::Percent | Token::TildeSlash | Token::StarStar
+            | Token::Ampersand | Token::Pipe | Token::Caret | Token::LessLess | Token::GreaterGreater => &[(Number, Number, Number)],
+            Token::Range => &[(Number, Number, Range)],
+            Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual => &[(Number, Number, Boolean), (String, String, Boolean), (List, List, Boolean), (Tuple, Tuple, Boolean)],
+            Token::EqualEqual | Token::BangEqual | Token::Is => return Ok(Boolean),
+            _ => return Ok(Type::Any)
+        };
+        let results: Vec<&Type> = rules.iter()
+            .filter(|(l, r, _)| l.accepts(left) && r.accepts(right))
+            .map(|(_, _, out)| out)
+            .collect();
+        match results.first() {
+            None => Err(Error::new(op.loc, format!("Type Error: Cannot apply '{}' to '{}' and '{}'.", op.token, left, right).as_str())),
+            Some(first) if results.iter().all(|out| out == first) => Ok((*first).clone()),
+            Some(_) => Ok(Type::Any)
+        }
+    }
+}
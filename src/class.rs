@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use crate::ast::Param;
+use crate::function::Function;
+use crate::scanner::LocToken;
+use crate::token::Value;
+
+pub struct Class {
+    pub name: LocToken,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, Rc<Function>>,
+    pub traits: Vec<Rc<Trait>>,
+}
+
+impl Class {
+    // the class's own methods win over inherited ones, which win over trait defaults
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref().and_then(|superclass| superclass.find_method(name))
+                .or_else(|| self.traits.iter().find_map(|t| t.defaults.get(name).cloned())),
+        }
+    }
+
+    // every method find_method can reach, sorted and without duplicates
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned()
+            .chain(self.superclass.iter().flat_map(|superclass| superclass.method_names()))
+            .chain(self.traits.iter().flat_map(|t| t.defaults.keys().cloned()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn implements(&self, t: &Rc<Trait>) -> bool {
+        self.traits.iter().any(|own| Rc::ptr_eq(own, t)) || self.superclass.as_ref().is_some_and(|superclass| superclass.implements(t))
+    }
+
+    pub fn inherits(self: &Rc<Self>, class: &Rc<Class>) -> bool {
+        Rc::ptr_eq(self, class) || self.superclass.as_ref().is_some_and(|superclass| superclass.inherits(class))
+    }
+}
+
+pub struct Trait {
+    pub name: LocToken,
+    pub required: Vec<(LocToken, Vec<Param>)>,
+    pub defaults: HashMap<String, Rc<Function>>,
+}
+
+impl Trait {
+    // every method of the trait with its parameters, required or not
+    pub fn methods(&self) -> Vec<(String, &[Param])> {
+        self.required.iter().map(|(name, params)| (name.token.to_string(), params.as_slice()))
+            .chain(self.defaults.iter().map(|(name, method)| (name.clone(), method.params.as_slice())))
+            .collect()
+    }
+}
+
+impl Debug for Trait {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<trait {}>", self.name.token)
+    }
+}
+
+impl PartialEq for Trait {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for Class {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class {}>", self.name.token)
+    }
+}
+
+// classes and instances are only ever equal to themselves, unless an instance defines 'eq'
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: HashMap<String, Value>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Instance { class, fields: HashMap::new() }
+    }
+}
+
+impl Debug for Instance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} instance>", self.class.name.token)
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -1,32 +1,64 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
 use std::cell::RefCell;
 use crate::icps::Error;
-use crate::scanner::LocToken;
+use crate::scanner::{Loc, LocToken};
 use crate::token::Value;
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub values: HashMap<String, Value>,
+    // where each constant in this scope was declared, so reassigning one can point back at it
+    pub constants: HashMap<String, Loc>,
+    // declared without a value yet, which is different from holding null
+    pub uninitialized: HashSet<String>,
     pub enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
-        Environment { values: HashMap::new(), enclosing: None }
+        Environment { values: HashMap::new(), constants: HashMap::new(), uninitialized: HashSet::new(), enclosing: None }
     }
 
     pub fn new_local(enclosing: Rc<RefCell<Environment>>) -> Self {
-        Environment { values: HashMap::new(), enclosing: Some(enclosing) }
+        Environment { values: HashMap::new(), constants: HashMap::new(), uninitialized: HashSet::new(), enclosing: Some(enclosing) }
     }
 
-    pub fn define(&mut self, name: &LocToken, value: Value) {
+    pub fn declare(&mut self, name: &LocToken) -> Result<(), Error> {
+        self.define(name, Value::Null)?;
+        self.uninitialized.insert(name.token.to_string());
+        Ok(())
+    }
+
+    // a name can be declared again in the same scope, unless it is a constant there
+    pub fn define(&mut self, name: &LocToken, value: Value) -> Result<(), Error> {
+        self.redeclare(name)?;
+        self.uninitialized.remove(&name.token.to_string());
+        self.values.insert(name.token.to_string(), value);
+        Ok(())
+    }
+
+    pub fn define_constant(&mut self, name: &LocToken, value: Value) -> Result<(), Error> {
+        self.redeclare(name)?;
+        self.uninitialized.remove(&name.token.to_string());
+        self.constants.insert(name.token.to_string(), name.loc);
         self.values.insert(name.token.to_string(), value);
+        Ok(())
+    }
+
+    fn redeclare(&self, name: &LocToken) -> Result<(), Error> {
+        match self.constants.get(&name.token.to_string()) {
+            Some(declared) => Err(Error::new(name.loc, format!("Runtime Error: Cannot redeclare constant '{}' declared at [{}:{}].", name.token, declared.line, declared.col).as_str())),
+            None => Ok(())
+        }
     }
 
     pub fn assign(&mut self, name: LocToken, value: Value) -> Result<(), Error> {
-        if let Some(v) = self.values.get_mut(&name.token.to_string()) {
+        if let Some(declared) = self.constants.get(&name.token.to_string()) {
+            Err(Error::new(name.loc, format!("Runtime Error: Cannot assign to constant '{}' declared at [{}:{}].", name.token, declared.line, declared.col).as_str()))
+        } else if let Some(v) = self.values.get_mut(&name.token.to_string()) {
             *v = value;
+            self.uninitialized.remove(&name.token.to_string());
             Ok(())
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(name, value)
@@ -37,6 +69,7 @@ impl Environment {
 
     pub fn get(&self, name: &LocToken) -> Result<Value, Error> {
         match self.values.get(&name.token.to_string()) {
+            Some(_) if self.uninitialized.contains(&name.token.to_string()) => Err(Error::new(name.loc, format!("Runtime Error: Cannot use variable '{}' before assignment.", name.token).as_str())),
             Some(value) => Ok(value.clone()),
             None => {
                 match &self.enclosing {
@@ -46,4 +79,32 @@ impl Environment {
             }
         }
     }
+
+    pub fn defined(&self, name: &str) -> bool {
+        self.values.contains_key(name) || self.enclosing.as_ref().is_some_and(|enclosing| enclosing.borrow().defined(name))
+    }
+
+    // what locals() sees, every scope out to but not including the global one, with inner names shadowing outer ones,
+    // or the globals themselves when that is the scope in use
+    pub fn locals(&self) -> BTreeMap<String, Value> {
+        let mut values = match &self.enclosing {
+            Some(enclosing) if enclosing.borrow().enclosing.is_some() => enclosing.borrow().locals(),
+            Some(_) => BTreeMap::new(),
+            None => return self.globals(),
+        };
+        values.extend(self.initialized());
+        values
+    }
+
+    // what globals() sees, leaving out the builtins every script starts with, which are the natives still bound to their own name
+    pub fn globals(&self) -> BTreeMap<String, Value> {
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().globals(),
+            None => self.initialized().filter(|(name, value)| !matches!(value, Value::Native(native) if native.name == name)).collect(),
+        }
+    }
+
+    fn initialized(&self) -> impl Iterator<Item = (String, Value)> + '_ {
+        self.values.iter().filter(|(name, _)| !self.uninitialized.contains(*name)).map(|(name, value)| (name.clone(), value.clone()))
+    }
 }
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use crate::ast::{self, Param, Stmt};
+use crate::class::Instance;
+use crate::environment::Environment;
+use crate::scanner::LocToken;
+use crate::token::{Token, Value};
+
+// evaluated positional and named arguments of a call
+pub type Arguments = (Vec<Value>, Vec<(LocToken, Value)>);
+
+pub struct Function {
+    pub name: Option<LocToken>,
+    pub params: Vec<Param>,
+    pub body: Vec<Stmt>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub kind: Kind,
+}
+
+// calling a generator hands back a suspended Generator and calling an async function a scheduled Task, instead of running the body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Function,
+    Generator,
+    Async,
+}
+
+impl Kind {
+    pub fn of(body: &[Stmt], is_async: bool) -> Kind {
+        if is_async {
+            Kind::Async
+        } else if ast::yields(body) {
+            Kind::Generator
+        } else {
+            Kind::Function
+        }
+    }
+}
+
+impl Function {
+    pub fn signature(&self) -> String {
+        let params = self.params.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+        match &self.name {
+            Some(name) => format!("{}({})", name.token, params),
+            None => format!("fn ({})", params),
+        }
+    }
+
+    // the same method with 'this' bound to the instance it was looked up on
+    pub fn bind(&self, instance: &Rc<RefCell<Instance>>) -> Function {
+        let mut env = Environment::new_local(self.closure.clone());
+        env.values.insert(Token::This.to_string(), Value::Instance(instance.clone()));
+        Function {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: self.body.clone(),
+            closure: Rc::new(RefCell::new(env)),
+            kind: self.kind,
+        }
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {}>", name.token),
+            None => write!(f, "<fn>"),
+        }
+    }
+}
+
+// functions are only ever equal to themselves
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
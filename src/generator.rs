@@ -0,0 +1,323 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use crate::ast::{self, Expr, Stmt};
+use crate::environment::Environment;
+use crate::function::Function;
+use crate::icps::{Error, Unwind};
+use crate::interpreter::Interpreter;
+use crate::scanner::LocToken;
+use crate::token::Value;
+
+// a paused generator keeps its own stack of frames instead of living on the Rust stack, so it can stop at a yield and pick up there later
+pub struct Generator {
+    pub function: Rc<Function>,
+    frames: Vec<Frame>,
+    // the statement an async function is suspended in, finished with the awaited value when it resumes
+    pending: Option<(Stmt, Rc<RefCell<Environment>>)>,
+}
+
+// why a generator stopped running
+pub enum Step {
+    Yield(Value),
+    Await(Value),
+    Done(Value),
+}
+
+enum Frame {
+    Block { stmts: Vec<Stmt>, index: usize, env: Rc<RefCell<Environment>> },
+    While { condition: Expr, body: Stmt, env: Rc<RefCell<Environment>> },
+    For { name: LocToken, iter: Iter, body: Stmt, env: Rc<RefCell<Environment>> },
+    // sits under the frames of whichever part of a try statement is running, with the error or return to pass on once finally is done
+    Try { catch: Option<(Option<LocToken>, Vec<Stmt>)>, finally: Option<Vec<Stmt>>, env: Rc<RefCell<Environment>>, stage: Stage, rethrow: Option<Error> },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Body,
+    Catch,
+    Finally,
+}
+
+// what a for loop walks over, one value at a time
+pub enum Iter {
+    Range(f64, f64),
+    List(Rc<RefCell<Vec<Value>>>, usize),
+    Tuple(Rc<Vec<Value>>, usize),
+    Items(std::vec::IntoIter<Value>),
+    Generator(Rc<RefCell<Generator>>, LocToken),
+}
+
+impl Generator {
+    // the body starts suspended in the environment its arguments were bound in
+    pub fn new(function: Rc<Function>, env: Rc<RefCell<Environment>>) -> Self {
+        let stmts = function.body.clone();
+        Generator { function, frames: vec![Frame::Block { stmts, index: 0, env }], pending: None }
+    }
+
+    // runs until the next yield or await, sent is the value of the await it is suspended at or the error it throws
+    pub fn resume(&mut self, interpreter: &mut Interpreter, sent: Result<Value, Error>) -> Result<Step, Error> {
+        // a return here ends the generator rather than a call, so it cannot be a tail call
+        let tail_calls = std::mem::replace(&mut interpreter.tail_calls, false);
+        let out = match self.pending.take() {
+            // finishing the suspended statement can still return or fail, which has to pass through any finally around it
+            Some((stmt, env)) => {
+                let finished = match (&stmt, sent) {
+                    (_, Err(e)) => Err(e),
+                    (Stmt::Return(keyword, _), Ok(sent)) => Err(Error::unwind(keyword.loc, "Runtime Error: Cannot return from top-level code.", Unwind::Return(sent))),
+                    (_, Ok(sent)) => in_env(interpreter, &env, |i| i.finish_await(&stmt, sent)).map(|_| ()),
+                };
+                finished.or_else(|e| self.unwind(e)).and_then(|_| self.run(interpreter))
+            }
+            None => self.run(interpreter),
+        };
+        interpreter.tail_calls = tail_calls;
+        match out {
+            Ok(Step::Done(value)) => {
+                self.frames.clear();
+                Ok(Step::Done(value))
+            }
+            Ok(step) => Ok(step),
+            Err(mut e) => {
+                self.frames.clear();
+                match e.unwind.take() {
+                    Some(Unwind::Return(value)) => Ok(Step::Done(value)),
+                    unwind => {
+                        e.unwind = unwind;
+                        Err(e)
+                    }
+                }
+            }
+        }
+    }
+
+    // an error or return inside a try statement goes to its catch or finally instead of ending the generator,
+    // though a generator dropped while suspended never runs the finally blocks it is inside
+    fn run(&mut self, interpreter: &mut Interpreter) -> Result<Step, Error> {
+        while !self.frames.is_empty() {
+            match self.advance(interpreter) {
+                Ok(Some(step)) => return Ok(step),
+                Ok(None) => {}
+                Err(e) => self.unwind(e)?,
+            }
+        }
+        Ok(Step::Done(Value::Null))
+    }
+
+    fn advance(&mut self, interpreter: &mut Interpreter) -> Result<Option<Step>, Error> {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        match frame {
+            Frame::Block { stmts, index, env } => {
+                if *index >= stmts.len() {
+                    self.frames.pop();
+                    return Ok(None);
+                }
+                let stmt = stmts[*index].clone();
+                let env = env.clone();
+                *index += 1;
+                return self.step(interpreter, stmt, env);
+            }
+            Frame::While { condition, body, env } => {
+                let (condition, body, env) = (condition.clone(), body.clone(), env.clone());
+                if in_env(interpreter, &env, |i| i.branch(&condition))? {
+                    self.push(body, env);
+                } else {
+                    self.frames.pop();
+                }
+            }
+            Frame::For { name, iter, body, env } => {
+                let (name, body, env) = (name.clone(), body.clone(), env.clone());
+                match iter.next(interpreter)? {
+                    Some(value) => {
+                        let mut scope = Environment::new_local(env);
+                        scope.define_constant(&name, value)?;
+                        self.push(body, Rc::new(RefCell::new(scope)));
+                    }
+                    None => {
+                        self.frames.pop();
+                    }
+                }
+            }
+            // the part above finished normally, so finally runs next, or the statement is done
+            Frame::Try { finally: Some(finally), env, stage, .. } if *stage != Stage::Finally => {
+                let block = Frame::Block { stmts: finally.clone(), index: 0, env: Rc::new(RefCell::new(Environment::new_local(env.clone()))) };
+                *stage = Stage::Finally;
+                self.frames.push(block);
+            }
+            Frame::Try { rethrow, .. } => {
+                let rethrow = rethrow.take();
+                self.frames.pop();
+                if let Some(e) = rethrow {
+                    return Err(e);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // drops frames down to the nearest try statement that catches the error or has a finally to run first
+    fn unwind(&mut self, mut error: Error) -> Result<(), Error> {
+        while let Some(frame) = self.frames.pop() {
+            let (catch, finally, env, stage) = match frame {
+                Frame::Try { catch, finally, env, stage, .. } => (catch, finally, env, stage),
+                _ => continue,
+            };
+            if let (Stage::Body, Some((name, handler))) = (stage, &catch) {
+                let caught = match error.unwind.take() {
+                    None => Some(Value::Error(error.message().to_string(), error.loc())),
+                    Some(Unwind::Throw(value)) => Some(value),
+                    unwind => {
+                        error.unwind = unwind;
+                        None
+                    }
+                };
+                if let Some(value) = caught {
+                    let mut scope = Environment::new_local(env.clone());
+                    if let Some(name) = name {
+                        scope.define(name, value)?;
+                    }
+                    let block = Frame::Block { stmts: handler.clone(), index: 0, env: Rc::new(RefCell::new(scope)) };
+                    self.frames.push(Frame::Try { catch, finally, env, stage: Stage::Catch, rethrow: None });
+                    self.frames.push(block);
+                    return Ok(());
+                }
+            }
+            if let (Some(block), false) = (&finally, stage == Stage::Finally) {
+                let block = Frame::Block { stmts: block.clone(), index: 0, env: Rc::new(RefCell::new(Environment::new_local(env.clone()))) };
+                self.frames.push(Frame::Try { catch, finally, env, stage: Stage::Finally, rethrow: Some(error) });
+                self.frames.push(block);
+                return Ok(());
+            }
+        }
+        Err(error)
+    }
+
+    // statements that cannot suspend run straight through, the rest become frames
+    fn step(&mut self, interpreter: &mut Interpreter, stmt: Stmt, env: Rc<RefCell<Environment>>) -> Result<Option<Step>, Error> {
+        if let Some(operand) = ast::awaited(&stmt) {
+            let value = in_env(interpreter, &env, |i| i.evaluate(operand))?;
+            self.pending = Some((stmt, env));
+            return Ok(Some(Step::Await(value)));
+        }
+        if !ast::suspends(std::slice::from_ref(&stmt)) {
+            in_env(interpreter, &env, |i| i.execute(&stmt))?;
+            return Ok(None);
+        }
+        match stmt {
+            Stmt::Yield(_, value) => Ok(Some(Step::Yield(in_env(interpreter, &env, |i| i.evaluate(&value))?))),
+            Stmt::Block(stmts) => {
+                self.frames.push(Frame::Block { stmts, index: 0, env: Rc::new(RefCell::new(Environment::new_local(env))) });
+                Ok(None)
+            }
+            Stmt::If(condition, then_branch, else_branch) => {
+                if in_env(interpreter, &env, |i| i.branch(&condition))? {
+                    self.push(*then_branch, env);
+                } else if let Some(else_branch) = else_branch {
+                    self.push(*else_branch, env);
+                }
+                Ok(None)
+            }
+            Stmt::While(condition, body) => {
+                self.frames.push(Frame::While { condition: *condition, body: *body, env });
+                Ok(None)
+            }
+            Stmt::Try(body, catch, finally) => {
+                let block = Frame::Block { stmts: body, index: 0, env: Rc::new(RefCell::new(Environment::new_local(env.clone()))) };
+                self.frames.push(Frame::Try { catch, finally, env, stage: Stage::Body, rethrow: None });
+                self.frames.push(block);
+                Ok(None)
+            }
+            Stmt::For(name, iterable, body) => {
+                let value = in_env(interpreter, &env, |i| i.evaluate(&iterable))?;
+                let iter = Iter::new(value, &Interpreter::get_loc_token_from_expr(&iterable))?;
+                let name = Interpreter::loop_variable(&name, &iterable);
+                self.frames.push(Frame::For { name, iter, body: *body, env });
+                Ok(None)
+            }
+            // the parser keeps yields and awaits out of everything else
+            _ => Ok(None)
+        }
+    }
+
+    fn push(&mut self, stmt: Stmt, env: Rc<RefCell<Environment>>) {
+        self.frames.push(Frame::Block { stmts: vec![stmt], index: 0, env });
+    }
+}
+
+fn in_env<T>(interpreter: &mut Interpreter, env: &Rc<RefCell<Environment>>, f: impl FnOnce(&mut Interpreter) -> Result<T, Error>) -> Result<T, Error> {
+    let previous = std::mem::replace(&mut interpreter.env, env.clone());
+    let out = f(interpreter);
+    interpreter.env = previous;
+    out
+}
+
+impl Debug for Generator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.function.name {
+            Some(name) => write!(f, "<generator {}>", name.token),
+            None => write!(f, "<generator>"),
+        }
+    }
+}
+
+// generators are only ever equal to themselves
+impl PartialEq for Generator {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Iter {
+    pub fn new(value: Value, loc: &LocToken) -> Result<Iter, Error> {
+        match value {
+            Value::Range(start, end) => Ok(Iter::Range(start, end)),
+            Value::List(items) => Ok(Iter::List(items, 0)),
+            Value::Tuple(items) => Ok(Iter::Tuple(items, 0)),
+            Value::String(s) => Ok(Iter::Items(s.chars().map(|c| Value::String(c.to_string())).collect::<Vec<_>>().into_iter())),
+            Value::Map(entries) => Ok(Iter::Items(entries.borrow().keys().map(|k| Value::String(k.clone())).collect::<Vec<_>>().into_iter())),
+            Value::Generator(generator) => Ok(Iter::Generator(generator, loc.clone())),
+            other => Err(Error::new(loc.loc, format!("Runtime Error: Cannot iterate over '{}'.", other.repr()).as_str()))
+        }
+    }
+
+    // ranges include their end
+    pub fn next(&mut self, interpreter: &mut Interpreter) -> Result<Option<Value>, Error> {
+        match self {
+            Iter::Range(next, end) => {
+                if *next > *end {
+                    return Ok(None);
+                }
+                *next += 1.0;
+                Ok(Some(Value::Number(*next - 1.0)))
+            }
+            Iter::List(items, index) => {
+                let item = items.borrow().get(*index).cloned();
+                *index += 1;
+                Ok(item)
+            }
+            Iter::Tuple(items, index) => {
+                let item = items.get(*index).cloned();
+                *index += 1;
+                Ok(item)
+            }
+            Iter::Items(items) => Ok(items.next()),
+            Iter::Generator(generator, loc) => resume(generator, interpreter, loc),
+        }
+    }
+}
+
+// a generator that ends up resuming itself is an error rather than a panic, and only async functions can await
+pub fn resume(generator: &Rc<RefCell<Generator>>, interpreter: &mut Interpreter, loc: &LocToken) -> Result<Option<Value>, Error> {
+    let step = match generator.try_borrow_mut() {
+        Ok(mut generator) => generator.resume(interpreter, Ok(Value::Null))?,
+        Err(_) => return Err(Error::new(loc.loc, "Runtime Error: Generator is already running."))
+    };
+    match step {
+        Step::Yield(value) => Ok(Some(value)),
+        Step::Done(_) => Ok(None),
+        Step::Await(_) => Err(Error::new(loc.loc, "Runtime Error: A generator cannot await."))
+    }
+}
@@ -4,8 +4,11 @@ use std::io::{self, BufRead, Write};
 use std::path::Path;
 use std::process;
 use lazy_static::lazy_static;
-use crate::{interpreter, parser, scanner};
-use crate::scanner::Loc;
+use crate::{checker, interpreter, parser, scanner};
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::function::{Arguments, Function};
+use crate::scanner::{Loc, LocToken};
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 use rustyline::history::FileHistory;
@@ -67,12 +70,14 @@ pub fn run_prompt(interpreter: &mut Interpreter) -> Result<(), ReadlineError> {
 pub fn run(source: &str, interpreter: &mut Interpreter) -> Result<(), Error> {
     let mut scanner = scanner::Scanner::new(source);
     let scanned = scanner.scan();
+    let max_depth = interpreter.max_depth();
     match scanned {
         Ok(tokens) => {
-            let mut parser = parser::Parser::new(&tokens);
+            let mut parser = parser::Parser::new(&tokens, &mut interpreter.operators, max_depth);
             match parser.parse() {
                 Ok(tree) => {
-                    return interpreter.interpret(tree);
+                    checker::Checker::new(&mut interpreter.types, max_depth).check(&tree)?;
+                    interpreter.interpret(tree)
                 }
                 Err(e) => Err(e),
             }
@@ -81,6 +86,22 @@ pub fn run(source: &str, interpreter: &mut Interpreter) -> Result<(), Error> {
     }
 }
 
+// runs source in env instead of the interpreter's own scope and gives back the value of its last statement when that is an expression,
+// the caller's scheduler drains whatever tasks it started. an isolated eval sees none of the caller's operators or annotations and leaves none behind
+pub fn eval(source: &str, interpreter: &mut Interpreter, env: Rc<RefCell<Environment>>, isolated: bool) -> Result<Value, Error> {
+    let tokens = scanner::Scanner::new(&format!("{}\n", source)).scan()?;
+    let max_depth = interpreter.max_depth();
+    let (mut own_operators, mut own_types) = (parser::Operators::new(), checker::Scope::new());
+    let (operators, types) = if isolated { (&mut own_operators, &mut own_types) } else { (&mut interpreter.operators, &mut interpreter.types) };
+    let tree = parser::Parser::new(&tokens, operators, max_depth).parse()?;
+    checker::Checker::new(types, max_depth).check(&tree)?;
+    let value = interpreter.execute_block(&tree, env)?;
+    match tree.last() {
+        Some(Stmt::Expression(_)) => Ok(value),
+        _ => Ok(Value::Null)
+    }
+}
+
 pub fn warn(line: usize, col: usize, message: &str) {
     eprintln!("[line {}, column {}] Warning: {}", line, col, message);
 }
@@ -90,10 +111,20 @@ pub fn panic(message: &str) {
     process::exit(70);
 }
 
-#[derive(Debug)]
+// non-error control flow that travels up through the interpreter as an 'Error' until something handles it
+#[derive(Clone, Debug)]
+pub enum Unwind {
+    Return(Value),
+    Throw(Value),
+    // 'return f(...)' hands the call back to the function that is returning so it runs without growing the stack
+    TailCall(Box<(Rc<Function>, LocToken, Arguments)>),
+}
+
+#[derive(Clone, Debug)]
 pub struct Error {
     loc: Loc,
     message: String,
+    pub unwind: Option<Unwind>,
 }
 
 impl Error {
@@ -101,6 +132,23 @@ impl Error {
         Self {
             loc,
             message: message.to_owned(),
+            unwind: None,
+        }
+    }
+
+    pub fn loc(&self) -> Loc {
+        self.loc
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn unwind(loc: Loc, message: &str, unwind: Unwind) -> Self {
+        Self {
+            loc,
+            message: message.to_owned(),
+            unwind: Some(unwind),
         }
     }
 }
@@ -1,28 +1,138 @@
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
-use crate::ast::{Expr, Stmt};
+use crate::ast::{self, Case, Expr, Param, Pattern, Stmt};
+use crate::builtins;
+use crate::checker::Scope;
+use crate::class::{self, Instance};
 use crate::environment::Environment;
-use crate::icps::Error;
+use crate::function::{Arguments, Function, Kind};
+use crate::generator::{Generator, Iter};
+use crate::scheduler::{self, Scheduler};
+use crate::icps::{Error, Unwind};
+use crate::parser::Operators;
 use crate::scanner::{Loc, LocToken};
 use crate::token::{Token::{self, *}, Value};
+use crate::variant::{self, Variant};
+
+#[derive(Clone, Copy)]
+enum Binding {
+    Variable,
+    Constant,
+    Assign,
+}
 
 pub struct Interpreter {
-    pub env: Environment,
+    pub env: Rc<RefCell<Environment>>,
+    pub operators: Operators,
+    pub types: Scope,
+    pub scheduler: Scheduler,
+    // whether a return can hand its call back to the function call it is returning from, which a try or a generator frame prevents
+    pub tail_calls: bool,
+    // how deeply evaluate is nested right now, past max_depth the native stack would run out
+    depth: usize,
+    max_depth: usize,
+    // the functions being called and where they were called from, innermost last
+    calls: Vec<(Rc<Function>, Loc)>,
+    // only booleans are allowed as conditions and as operands of '!', 'not', 'and', 'or' and 'xor'
+    strict_bool: bool,
 }
 
+pub const MAX_DEPTH: usize = 1000;
+// native stack one level of nesting can take in a debug build, measured on bracketed expressions in the parser which are the heaviest
+const FRAME_SIZE: usize = 64 * 1024;
+// the interpreter thread never asks for more stack than this, so it also bounds the depth that can be configured
+const MAX_STACK: usize = 1024 * 1024 * 1024;
+pub const DEPTH_LIMIT: usize = MAX_STACK / FRAME_SIZE - 16;
+// how many calls at each end of the traceback a stack overflow shows
+const TRACEBACK: usize = 5;
+
 impl Interpreter {
     pub fn new() -> Self {
-        Interpreter { env: Environment::new() }
+        let mut env = Environment::new();
+        builtins::define(&mut env);
+        Interpreter { env: Rc::new(RefCell::new(env)), operators: Operators::new(), types: Scope::new(), scheduler: Scheduler::new(), tail_calls: false, depth: 0, max_depth: MAX_DEPTH, calls: Vec::new(), strict_bool: false }
     }
 
+    // tasks still waiting to run get to finish once the statements are done
     pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), Error> {
         for stmt in stmts {
             self.execute(&stmt)?;
         }
-        Ok(())
+        scheduler::drain(self)
+    }
+
+    // completes a statement an async function was suspended in, now that its await has a value
+    pub fn finish_await(&mut self, stmt: &Stmt, value: Value) -> Result<(), Error> {
+        match stmt {
+            Stmt::Expression(expr) => match &**expr {
+                Expr::Assign(name, _) => self.env.borrow_mut().assign(name.clone(), value),
+                // like any assignment the target object is evaluated after the value
+                Expr::Set(object, name, _) => {
+                    let object = self.evaluate(object)?;
+                    Self::set_field(&object, name, value).map(|_| ())
+                }
+                _ => Ok(())
+            },
+            Stmt::Log(expr) => {
+                println!("{}", self.stringify(&value, &Self::get_loc_token_from_expr(expr))?);
+                Ok(())
+            }
+            Stmt::Declaration(keyword, patterns, _) => {
+                let binding = if keyword.token == Var { Binding::Variable } else { Binding::Constant };
+                self.bind_values(patterns, patterns[0].token(), vec![value], binding)
+            }
+            Stmt::Assign(patterns, equals, _) => self.bind_values(patterns, equals, vec![value], Binding::Assign),
+            _ => Ok(())
+        }
+    }
+
+    // the thread running the interpreter needs a stack of at least stack_size(max_depth) for the limit to be reached before the stack runs out
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth.min(DEPTH_LIMIT);
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn set_strict_bool(&mut self, strict_bool: bool) {
+        self.strict_bool = strict_bool;
+    }
+
+    pub fn stack_size(max_depth: usize) -> usize {
+        max_depth.min(DEPTH_LIMIT) * FRAME_SIZE + FRAME_SIZE * 16
     }
 
     pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, Error> {
+        if self.depth >= self.max_depth {
+            return Err(self.stack_overflow(Self::get_loc_token_from_expr(expr).loc));
+        }
+        self.depth += 1;
+        let out = self.evaluate_expr(expr);
+        self.depth -= 1;
+        out
+    }
+
+    // reported at the innermost call site, or at the expression itself when nesting alone went too deep
+    fn stack_overflow(&self, loc: Loc) -> Error {
+        let loc = match self.calls.last() {
+            Some((_, call)) => *call,
+            None => loc,
+        };
+        let mut message = format!("Runtime Error: Stack overflow, nesting went deeper than {}.", self.max_depth);
+        let frame = |(function, loc): &(Rc<Function>, Loc)| format!("\n    at {} called from [{}:{}]", function.signature(), loc.line, loc.col);
+        if self.calls.len() > 2 * TRACEBACK {
+            self.calls.iter().rev().take(TRACEBACK).for_each(|call| message.push_str(&frame(call)));
+            message.push_str(&format!("\n    ... {} more calls", self.calls.len() - 2 * TRACEBACK));
+            self.calls.iter().take(TRACEBACK).rev().for_each(|call| message.push_str(&frame(call)));
+        } else {
+            self.calls.iter().rev().for_each(|call| message.push_str(&frame(call)));
+        }
+        Error::new(loc, &message)
+    }
+
+    fn evaluate_expr(&mut self, expr: &Expr) -> Result<Value, Error> {
         match expr {
             Expr::Literal(token) => {
                 if token.token.is_valid_value() {
@@ -32,8 +142,20 @@ impl Interpreter {
                 }
             }
             Expr::Grouping(e) => self.evaluate(e),
+            Expr::Lambda(_, params, _, body) => Ok(Value::Function(Rc::new(Function {
+                name: None,
+                params: params.clone(),
+                body: body.clone(),
+                closure: self.env.clone(),
+                kind: Kind::of(body, false),
+            }))),
             Expr::Unary(op, re) => {
                 let right = self.evaluate(re)?;
+                if op.token == Minus {
+                    if let Some(method) = Self::method(&right, "neg") {
+                        return self.call(method, op, Vec::new());
+                    }
+                }
                 match op.token {
                     Minus => {
                         if let Value::Number(n) = right {
@@ -43,13 +165,10 @@ impl Interpreter {
                         }
                     }
 
-                    Bang => {
-                        if let Value::Boolean(b) = right {
-                            Ok(Value::Boolean(!b))
-                        } else {
-                            Err(Error::new(op.loc, "Runtime Error: Cannot negate non 'Boolean' expression."))
-                        }
-                    }
+                    // '!' is 'not' at unary precedence
+                    Bang | Not => Ok(Value::Boolean(!self.truthy(&right, Self::get_loc_token_from_expr(re).loc)?)),
+
+                    Tilde => Ok(Value::Number(!builtins::integer(&right, op)? as f64)),
 
                     _ => Err(Error::new(op.loc, "Runtime Error: Invalid unary operator"))
                 }
@@ -57,143 +176,48 @@ impl Interpreter {
             Expr::Binary(le, op, re) => {
                 let left = self.evaluate(le)?;
                 let right = self.evaluate(re)?;
-                match op.token {
-                    Plus => {
-                        match left {
-                            Value::Number(l) => {
-                                match right {
-                                    Value::Number(r) => Ok(Value::Number(l + r)),
-                                    Value::String(r) => Ok(Value::String(format!("{}{}", l, r))),
-                                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot add 'Number' with anything but 'Number' or 'String' or an expression evaluating to it"))
-                                }
-                            }
-                            Value::String(l) => {
-                                match right {
-                                    Value::Number(r) => Ok(Value::String(format!("{}{}", l, r))),
-                                    Value::String(r) => Ok(Value::String(format!("{}{}", l, r))),
-                                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot add 'String' with anything but 'String' or 'Number' or an expression evaluating to it"))
-                                }
-                            }
-                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot add anything to a non 'Number' or 'String' expression."))
-                        }
-                    }
-
-                    Minus => {
-                        match left {
-                            Value::Number(l) => {
-                                match right {
-                                    Value::Number(r) => Ok(Value::Number(l - r)),
-                                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot subtract 'Number' with anything but 'Number' or an expression evaluating to it"))
-                                }
-                            }
-                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot subtract anything from a non 'Number' expression."))
-                        }
-                    }
-
-                    Star => {
-                        match left {
-                            Value::Number(l) => {
-                                match right {
-                                    Value::Number(r) => Ok(Value::Number(l * r)),
-                                    Value::String(r) => Ok(Value::String(r.repeat(l.round() as usize))),
-                                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot multiply 'Number' with anything but 'Number' or 'String' or an expression evaluating to it"))
-                                }
-                            }
-                            Value::String(l) => {
-                                match right {
-                                    Value::Number(r) => Ok(Value::String(l.repeat(r.round() as usize))),
-                                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot multiply 'String' with anything but 'Number' or an expression evaluating to it"))
-                                }
-                            }
-                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot multiply anything with a non 'Number' or 'String' expression."))
-                        }
-                    }
-
-                    Slash => {
-                        match right {
-                            Value::Number(r) => {
-                                if r == 0.0 {
-                                    Err(Error::new(op.loc, "Runtime Error: Division by zero."))
-                                } else {
-                                    match left {
-                                        Value::Number(l) => Ok(Value::Number(l / r)),
-                                        _ => Err(Error::new(op.loc, "Runtime Error: Cannot divide anything but 'Number' or an expression evaluating to it"))
-                                    }
-                                }
-                            }
-                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot divide by anything but 'Number' or an expression evaluating to it"))
-                        }
-                    }
+                self.evaluate_binary(op, left, right)
+            }
 
-                    Range => {
-                        match left {
-                            Value::Number(l) => {
-                                match right {
-                                    Value::Number(r) => Ok(Value::Range(l, r)),
-                                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot create a range with anything but 'Number' or an expression evaluating to it"))
-                                }
-                            }
-                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot create a range with anything but 'Number' or an expression evaluating to it"))
-                        }
-                    }
+            Expr::Variable(token) => self.env.borrow().get(token),
 
-                    EqualEqual | BangEqual => {
-                        let comparison = match (&left, &right) {
-                            (Value::Number(l), Value::Number(r)) => l == r,
-                            (Value::String(l), Value::String(r)) => l == r,
-                            (Value::Boolean(l), Value::Boolean(r)) => l == r,
-                            (Value::Null, Value::Null) => true,
-                            _ => false
-                        };
+            Expr::Assign(token, value) => {
+                let value = self.evaluate(value)?;
+                self.env.borrow_mut().assign(token.clone(), value.clone())?;
+                Ok(value)
+            }
 
-                        Ok(Value::Boolean(if op.token == EqualEqual { comparison } else { !comparison }))
+            Expr::Comparison(first, rest) => {
+                let mut left = self.evaluate(first)?;
+                let mut result = Value::Boolean(true);
+                for (op, operand) in rest {
+                    let right = self.evaluate(operand)?;
+                    result = self.evaluate_binary(op, left, right.clone())?;
+                    if !self.truthy(&result, op.loc)? {
+                        break;
                     }
-
-                    Greater | GreaterEqual | Less | LessEqual => {
-                        match left {
-                            Value::Number(l) => {
-                                if let Value::Number(r) = right {
-                                    Ok(match op.token {
-                                        Greater => Value::Boolean(l > r),
-                                        GreaterEqual => Value::Boolean(l >= r),
-                                        Less => Value::Boolean(l < r),
-                                        LessEqual => Value::Boolean(l <= r),
-                                        _ => panic!()
-                                    })
-                                } else {
-                                    Err(Error::new(op.loc, "Runtime Error: Cannot compare 'Number' with anything but 'Number' or an expression evaluating to it"))
-                                }
-                            }
-                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot compare anything with a non 'Number' expression."))
-                        }
-                    }
-
-                    _ => Err(Error::new(op.loc, "Runtime Error: Invalid binary operator"))
+                    left = right;
                 }
+                Ok(result)
             }
 
-            Expr::Variable(token) => {
-                match self.env.get(token)? {
-                    Value::Null => Err(Error::new(token.loc, format!("Runtime Error: Cannot use variable '{}' before assignment.", token.token).as_str())),
+            Expr::Logical(le, op, re) if op.token == QuestionQuestion => {
+                match self.evaluate(le)? {
+                    Value::Null => self.evaluate(re),
                     v => Ok(v)
                 }
             }
 
-            Expr::Assign(token, value) => {
-                let value = self.evaluate(value)?;
-                self.env.assign(token.clone(), value.clone())?;
-                Ok(value)
-            }
-
+            // 'xor' always needs both sides, so only 'and' and 'or' can stop early
             Expr::Logical(le, op, re) => {
-                let left = self.evaluate(le)?.is_truthy();
+                let left = self.branch(le)?;
                 if op.token == Or && left {
                     return Ok(Value::Boolean(true));
                 }
                 if op.token == And && !left {
                     return Ok(Value::Boolean(false));
                 }
-                let right = self.evaluate(re)?.is_truthy();
+                let right = self.branch(re)?;
                 match op.token {
                     Or => Ok(Value::Boolean(left || right)),
                     Xor => Ok(Value::Boolean(left ^ right)),
@@ -202,139 +226,1078 @@ impl Interpreter {
                 }
             }
 
-            Expr::Call(_, _) => {
-                println!("Warning: Called evaluate expr on a non implemented operation!");
-                Ok(Value::Null)
+            Expr::Ternary(condition, _, then_value, else_value) => {
+                if self.branch(condition)? {
+                    self.evaluate(then_value)
+                } else {
+                    self.evaluate(else_value)
+                }
             }
 
-            Expr::Get(_, _) => {
-                println!("Warning: Called evaluate expr on a non implemented operation!");
-                Ok(Value::Null)
+            Expr::Call(_, _, _, _) | Expr::Index(_, _, _) | Expr::Get(_, _) | Expr::SafeGet(_, _) => Ok(self.access(expr)?.unwrap_or(Value::Null)),
+
+            Expr::List(_, items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.evaluate(item)?);
+                }
+                Ok(Value::List(Rc::new(RefCell::new(values))))
             }
 
-            Expr::Set(_, _, _) => {
-                println!("Warning: Called evaluate expr on a non implemented operation!");
-                Ok(Value::Null)
+            Expr::Tuple(_, items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.evaluate(item)?);
+                }
+                Ok(Value::Tuple(Rc::new(values)))
             }
 
-            Expr::Super(_) => {
-                println!("Warning: Called evaluate expr on a non implemented operation!");
-                Ok(Value::Null)
+            Expr::Map(_, entries) => {
+                let mut values = BTreeMap::new();
+                for (key, value) in entries {
+                    let value = self.evaluate(value)?;
+                    values.insert(key.token.to_string(), value);
+                }
+                Ok(Value::Map(Rc::new(RefCell::new(values))))
             }
 
-            Expr::This(_) => {
-                println!("Warning: Called evaluate expr on a non implemented operation!");
-                Ok(Value::Null)
+            Expr::Comprehension(bracket, body) => {
+                let function = Rc::new(Function { name: None, params: Vec::new(), body: body.clone(), closure: self.env.clone(), kind: Kind::Generator });
+                let env = Rc::new(RefCell::new(Environment::new_local(self.env.clone())));
+                let mut iter = Iter::Generator(Rc::new(RefCell::new(Generator::new(function, env))), bracket.clone());
+                let mut items = Vec::new();
+                while let Some(item) = iter.next(self)? {
+                    items.push(item);
+                }
+                if bracket.token != LeftBrace {
+                    return Ok(Value::List(Rc::new(RefCell::new(items))));
+                }
+                let mut entries = BTreeMap::new();
+                for item in items {
+                    match item {
+                        Value::Tuple(entry) => match (&entry[0], &entry[1]) {
+                            (Value::String(key), value) => entries.insert(key.clone(), value.clone()),
+                            (key, _) => return Err(Error::new(bracket.loc, format!("Runtime Error: Map keys must be strings, but found '{}'.", key.repr()).as_str()))
+                        },
+                        _ => unreachable!("map comprehensions yield (key, value) tuples")
+                    };
+                }
+                Ok(Value::Map(Rc::new(RefCell::new(entries))))
+            }
+
+            Expr::Set(object, name, value) => {
+                let object = self.evaluate(object)?;
+                let value = self.evaluate(value)?;
+                Self::set_field(&object, name, value)
+            }
+
+            Expr::Super(keyword, method) => {
+                let superclass = self.env.borrow().get(keyword)?;
+                let instance = self.env.borrow().get(&LocToken { token: This, loc: keyword.loc })?;
+                match (superclass, instance) {
+                    (Value::Class(superclass), Value::Instance(instance)) => match superclass.find_method(&method.token.to_string()) {
+                        Some(function) => Ok(Value::Function(Rc::new(function.bind(&instance)))),
+                        None => Err(Error::new(method.loc, format!("Runtime Error: Undefined method '{}' on superclass '{}'.", method.token, superclass.name.token).as_str()))
+                    },
+                    _ => Err(Error::new(keyword.loc, "Runtime Error: Can only use 'super' inside a method."))
+                }
+            }
+
+            Expr::This(keyword) => self.env.borrow().get(keyword),
+
+            // inside a task an await only suspends where the parser allows it, anywhere else it would have to block the task it is in
+            Expr::Await(keyword, operand) => {
+                if self.scheduler.in_task() {
+                    return Err(Error::new(keyword.loc, "Runtime Error: 'await' in an async function must start a statement, an initializer, an assignment or a return value."));
+                }
+                match self.evaluate(operand)? {
+                    Value::Task(task) => scheduler::run_until(self, &task, keyword),
+                    value => Ok(value)
+                }
+            }
+
+            Expr::Match(keyword, subject, arms) => {
+                let value = self.evaluate(subject)?;
+                for (case, body) in arms {
+                    let env = Rc::new(RefCell::new(Environment::new_local(self.env.clone())));
+                    if self.matches(case, &value, &env)? {
+                        let previous = std::mem::replace(&mut self.env, env);
+                        let out = self.evaluate(body);
+                        self.env = previous;
+                        return out;
+                    }
+                }
+                Err(Error::new(keyword.loc, format!("Runtime Error: No arm matched '{}'.", value.repr()).as_str()))
             }
         }
     }
 
+    // statements nest on the native stack as well, so they count toward the depth; one without a location is an empty block that goes no deeper
     pub fn execute(&mut self, stmt: &Stmt) -> Result<Value, Error> {
+        if self.depth >= self.max_depth {
+            if let Some(token) = Self::get_loc_token_from_stmt(stmt) {
+                return Err(self.stack_overflow(token.loc));
+            }
+        }
+        self.depth += 1;
+        let out = self.execute_stmt(stmt);
+        self.depth -= 1;
+        out
+    }
+
+    fn execute_stmt(&mut self, stmt: &Stmt) -> Result<Value, Error> {
         match stmt {
             Stmt::Expression(e) => self.evaluate(e),
             Stmt::Log(e) => {
                 match self.evaluate(e) {
                     Ok(v) => {
-                        println!("{}", v);
+                        println!("{}", self.stringify(&v, &Self::get_loc_token_from_expr(e))?);
                         Ok(v)
                     }
                     Err(e) => Err(e)
                 }
             }
 
-            Stmt::Declaration(name, initializer) => {
-                let value = match initializer {
-                    Some(i) => self.evaluate(i)?,
-                    None => Value::Null
+            Stmt::Declaration(keyword, patterns, values) => {
+                let binding = if keyword.token == Var { Binding::Variable } else { Binding::Constant };
+                self.destructure(patterns, patterns[0].token(), values, binding)?;
+                Ok(Value::Null)
+            }
+
+            Stmt::Assign(patterns, equals, values) => {
+                self.destructure(patterns, equals, values, Binding::Assign)?;
+                Ok(Value::Null)
+            }
+
+            Stmt::Function(name, params, _, body, is_async) => {
+                let function = Function {
+                    name: Some(name.clone()),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: self.env.clone(),
+                    kind: Kind::of(body, *is_async),
                 };
-                self.env.define(name, value);
+                self.env.borrow_mut().define(name, Value::Function(Rc::new(function)))?;
+                Ok(Value::Null)
+            }
+
+            Stmt::Class(name, superclass, traits, methods) => {
+                let superclass = match superclass {
+                    Some(expr) => match self.evaluate(expr)? {
+                        Value::Class(class) => Some(class),
+                        _ => return Err(Error::new(Self::get_loc_token_from_expr(expr).loc, "Runtime Error: Superclass must be a class."))
+                    },
+                    None => None
+                };
+                // methods of a subclass close over an environment holding 'super'
+                let closure = match &superclass {
+                    Some(class) => {
+                        let mut env = Environment::new_local(self.env.clone());
+                        env.define(&LocToken { token: Super, loc: name.loc }, Value::Class(class.clone()))?;
+                        Rc::new(RefCell::new(env))
+                    }
+                    None => self.env.clone()
+                };
+                let mut implemented = Vec::new();
+                for expr in traits {
+                    match self.evaluate(expr)? {
+                        Value::Trait(t) => implemented.push(t),
+                        _ => return Err(Error::new(Self::get_loc_token_from_expr(expr).loc, "Runtime Error: Can only implement traits."))
+                    }
+                }
+                let class = class::Class { name: name.clone(), superclass, methods: Self::methods(methods, &closure), traits: implemented };
+                Self::check_traits(&class)?;
+                self.env.borrow_mut().define(name, Value::Class(Rc::new(class)))?;
+                Ok(Value::Null)
+            }
+
+            Stmt::Trait(name, required, defaults) => {
+                let t = class::Trait { name: name.clone(), required: required.clone(), defaults: Self::methods(defaults, &self.env) };
+                self.env.borrow_mut().define(name, Value::Trait(Rc::new(t)))?;
+                Ok(Value::Null)
+            }
+
+            Stmt::Enum(name, variants) => {
+                let ty = Rc::new(variant::Enum {
+                    name: name.clone(),
+                    variants: variants.iter().map(|(variant, fields)| (variant.token.to_string(), fields.iter().map(|f| f.token.to_string()).collect())).collect(),
+                });
+                self.env.borrow_mut().define(name, Value::Enum(ty.clone()))?;
+                for (tag, (variant, _)) in variants.iter().enumerate() {
+                    let value = Self::constructor(&ty, tag);
+                    self.env.borrow_mut().define(variant, value)?;
+                }
                 Ok(Value::Null)
             }
 
-            Stmt::Block(stmts) => {
-                let previous = Rc::new(RefCell::new(self.env.clone()));
-                self.env = Environment::new_local(previous.clone());
-                let mut out = Ok(Value::Null);
-                for stmt in stmts {
-                    out = self.execute(stmt);
+            Stmt::Return(keyword, value) => {
+                if let (true, Some(Expr::Call(callee, paren, args, named))) = (self.tail_calls, value) {
+                    let callee = match self.access(callee)? {
+                        Some(callee) => callee,
+                        None => return Err(Error::unwind(keyword.loc, "Runtime Error: Cannot return from top-level code.", Unwind::Return(Value::Null)))
+                    };
+                    let (arguments, named) = self.arguments(args, named)?;
+                    let value = match callee {
+                        Value::Function(function) if function.kind == Kind::Function => {
+                            return Err(Error::unwind(keyword.loc, "Runtime Error: Cannot return from top-level code.", Unwind::TailCall(Box::new((function, paren.clone(), (arguments, named))))));
+                        }
+                        callee => self.call_with_named(callee, paren, arguments, named)?
+                    };
+                    return Err(Error::unwind(keyword.loc, "Runtime Error: Cannot return from top-level code.", Unwind::Return(value)));
                 }
-                self.env = previous.borrow().clone();
+                let value = match value {
+                    Some(v) => self.evaluate(v)?,
+                    None => Value::Null
+                };
+                Err(Error::unwind(keyword.loc, "Runtime Error: Cannot return from top-level code.", Unwind::Return(value)))
+            }
+
+            Stmt::Throw(keyword, value) => {
+                let value = self.evaluate(value)?;
+                // rethrowing a caught runtime error keeps where it originally happened
+                let (loc, message) = match &value {
+                    Value::Error(message, loc) => (*loc, message.clone()),
+                    v => (keyword.loc, format!("Runtime Error: Uncaught exception '{}'.", v))
+                };
+                Err(Error::unwind(loc, message.as_str(), Unwind::Throw(value)))
+            }
+
+            Stmt::Try(body, catch, finally) => {
+                let tail_calls = std::mem::replace(&mut self.tail_calls, false);
+                let out = self.try_statement(body, catch, finally);
+                self.tail_calls = tail_calls;
                 out
             }
 
+            // operators are registered by the parser, there is nothing left to do at runtime
+            Stmt::Infix(_, _, _) => Ok(Value::Null),
+
+            Stmt::Block(stmts) => self.execute_block(stmts, Rc::new(RefCell::new(Environment::new_local(self.env.clone())))),
+
             Stmt::If(condition, then_branch, else_branch) => {
-                match self.evaluate(condition)? {
-                    Value::Boolean(b) => {
-                        if b {
-                            self.execute(then_branch)
-                        } else {
-                            match else_branch {
-                                Some(e) => self.execute(e),
-                                None => Ok(Value::Null)
-                            }
-                        }
+                if self.branch(condition)? {
+                    self.execute(then_branch)
+                } else {
+                    match else_branch {
+                        Some(e) => self.execute(e),
+                        None => Ok(Value::Null)
                     }
-                    _ => Err(Error::new(Self::get_loc_token_from_expr(condition).loc, "Runtime Error: Invalid condition."))
                 }
             }
 
             Stmt::While(condition, body) => {
-                while self.evaluate(condition)?.is_truthy() {
+                while self.branch(condition)? {
                     self.execute(body)?;
                 }
                 Ok(Value::Null)
             }
 
             Stmt::For(name, iterable, body) => {
-                let range = match self.evaluate(iterable)? {
-                    Value::Range(l, r) => l..r,
-                    _ => return Err(Error::new(Self::get_loc_token_from_expr(iterable).loc, "Runtime Error: For loop iterable must be a range."))
+                let mut iter = Iter::new(self.evaluate(iterable)?, &Self::get_loc_token_from_expr(iterable))?;
+                let actual = Self::loop_variable(name, iterable);
+                self.for_each(&actual, &mut iter, body)
+            }
+
+            Stmt::Yield(keyword, _) => Err(Error::new(keyword.loc, "Runtime Error: Cannot yield outside of a generator.")),
+
+            _ => {
+                Err(Error::new(Loc { line: 0, col: 0, idx: 0 }, "Runtime Error: Not Implemented."))
+            }
+        }
+    }
+
+    // the loop variable is a constant so the body cannot skip around in it,
+    // and each iteration gets its own scope so closures keep the value they were created with
+    fn for_each(&mut self, actual: &LocToken, iter: &mut Iter, body: &Stmt) -> Result<Value, Error> {
+        while let Some(value) = iter.next(self)? {
+            let mut scope = Environment::new_local(self.env.clone());
+            scope.define_constant(actual, value)?;
+            let previous = std::mem::replace(&mut self.env, Rc::new(RefCell::new(scope)));
+            let out = self.execute(body);
+            self.env = previous;
+            out?;
+        }
+        Ok(Value::Null)
+    }
+
+    // without a name the loop variable is 'i'
+    pub fn loop_variable(name: &Option<LocToken>, iterable: &Expr) -> LocToken {
+        match name {
+            Some(n) => n.clone(),
+            None => LocToken {
+                token: Identifier("i".to_string()),
+                loc: Self::get_loc_token_from_expr(iterable).loc,
+            },
+        }
+    }
+
+    fn try_statement(&mut self, body: &[Stmt], catch: &Option<(Option<LocToken>, Vec<Stmt>)>, finally: &Option<Vec<Stmt>>) -> Result<Value, Error> {
+        let mut out = self.execute_block(body, Rc::new(RefCell::new(Environment::new_local(self.env.clone()))));
+        if let (Err(e), Some((name, handler))) = (&mut out, catch) {
+            let caught = match e.unwind.take() {
+                None => Some(Value::Error(e.message().to_string(), e.loc())),
+                Some(Unwind::Throw(value)) => Some(value),
+                unwind => {
+                    e.unwind = unwind;
+                    None
+                }
+            };
+            if let Some(value) = caught {
+                let mut env = Environment::new_local(self.env.clone());
+                if let Some(name) = name {
+                    env.define(name, value)?;
+                }
+                out = self.execute_block(handler, Rc::new(RefCell::new(env)));
+            }
+        }
+        // an error or return from the finally block replaces whatever was already on its way out
+        if let Some(finally) = finally {
+            self.execute_block(finally, Rc::new(RefCell::new(Environment::new_local(self.env.clone()))))?;
+        }
+        out
+    }
+
+    fn arguments(&mut self, args: &[Expr], named: &[(LocToken, Expr)]) -> Result<Arguments, Error> {
+        let mut arguments = Vec::new();
+        for arg in args {
+            arguments.push(self.evaluate(arg)?);
+        }
+        let mut named_arguments = Vec::new();
+        for (name, arg) in named {
+            named_arguments.push((name.clone(), self.evaluate(arg)?));
+        }
+        Ok((arguments, named_arguments))
+    }
+
+    // every condition goes through here, false, null and the empty string are falsy unless only booleans are allowed
+    pub fn truthy(&self, value: &Value, loc: Loc) -> Result<bool, Error> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            value if self.strict_bool => Err(Error::new(loc, format!("Runtime Error: Condition must be a 'Boolean', but found '{}'.", value.type_name()).as_str())),
+            value => Ok(value.is_truthy())
+        }
+    }
+
+    pub fn branch(&mut self, condition: &Expr) -> Result<bool, Error> {
+        let value = self.evaluate(condition)?;
+        self.truthy(&value, Self::get_loc_token_from_expr(condition).loc)
+    }
+
+    pub fn execute_block(&mut self, stmts: &[Stmt], env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+        let previous = std::mem::replace(&mut self.env, env);
+        let mut out = Ok(Value::Null);
+        for stmt in stmts {
+            out = self.execute(stmt);
+            if out.is_err() {
+                break;
+            }
+        }
+        self.env = previous;
+        out
+    }
+
+    pub fn call(&mut self, callee: Value, paren: &LocToken, arguments: Vec<Value>) -> Result<Value, Error> {
+        self.call_with_named(callee, paren, arguments, Vec::new())
+    }
+
+    pub fn call_with_named(&mut self, callee: Value, paren: &LocToken, arguments: Vec<Value>, named: Vec<(LocToken, Value)>) -> Result<Value, Error> {
+        match callee {
+            Value::Native(native) => {
+                if let Some((name, _)) = named.first() {
+                    return Err(Error::new(name.loc, format!("Runtime Error: Native function '{}' does not take named arguments.", native.name).as_str()));
+                }
+                if !native.arity.contains(&arguments.len()) {
+                    let expected = if native.arity.start() == native.arity.end() { native.arity.start().to_string() } else { format!("{} to {}", native.arity.start(), native.arity.end()) };
+                    return Err(Error::new(paren.loc, format!("Runtime Error: Expected {} arguments but got {}.", expected, arguments.len()).as_str()));
+                }
+                (native.func)(self, paren, arguments)
+            }
+            Value::Function(function) => {
+                self.calls.push((function.clone(), paren.loc));
+                let out = self.call_function(function, paren, arguments, named);
+                self.calls.pop();
+                out
+            }
+            Value::Constructor(ty, tag) => {
+                let (variant, fields) = &ty.variants[tag];
+                if let Some((name, _)) = named.first() {
+                    return Err(Error::new(name.loc, format!("Runtime Error: Variant '{}' does not take named arguments.", variant).as_str()));
+                }
+                if arguments.len() != fields.len() {
+                    return Err(Error::new(paren.loc, format!("Runtime Error: Expected {} arguments but got {} constructing '{}'.", fields.len(), arguments.len(), variant).as_str()));
+                }
+                Ok(Value::Variant(Rc::new(Variant { ty: ty.clone(), tag, values: arguments })))
+            }
+            // a new instance, passed through 'init' when the class has one
+            Value::Class(class) => {
+                let instance = Rc::new(RefCell::new(Instance::new(class.clone())));
+                match class.find_method("init") {
+                    Some(init) => {
+                        self.call_with_named(Value::Function(Rc::new(init.bind(&instance))), paren, arguments, named)?;
+                    }
+                    None if !arguments.is_empty() || !named.is_empty() => {
+                        return Err(Error::new(paren.loc, format!("Runtime Error: Expected 0 arguments but got {} constructing '{}'.", arguments.len() + named.len(), class.name.token).as_str()));
+                    }
+                    None => {}
+                }
+                Ok(Value::Instance(instance))
+            }
+            _ => Err(Error::new(paren.loc, "Runtime Error: Can only call functions and classes."))
+        }
+    }
+
+    fn methods(methods: &[Stmt], closure: &Rc<RefCell<Environment>>) -> HashMap<std::string::String, Rc<Function>> {
+        let mut functions = HashMap::new();
+        for method in methods {
+            if let Stmt::Function(method, params, _, body, is_async) = method {
+                functions.insert(method.token.to_string(), Rc::new(Function {
+                    name: Some(method.clone()),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: closure.clone(),
+                    kind: Kind::of(body, *is_async),
+                }));
+            }
+        }
+        functions
+    }
+
+    // every method a trait declares has to be there, taking the same number of parameters
+    fn check_traits(class: &class::Class) -> Result<(), Error> {
+        let arity = |params: &[Param]| (params.iter().filter(|p| !p.variadic).count(), params.iter().any(|p| p.variadic));
+        for t in &class.traits {
+            for (name, params) in t.methods() {
+                let method = match class.find_method(&name) {
+                    Some(method) => method,
+                    None => return Err(Error::new(class.name.loc, format!("Runtime Error: Class '{}' does not implement '{}({})' required by trait '{}'.", class.name.token, name, params.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "), t.name.token).as_str()))
                 };
-                let previous = Rc::new(RefCell::new(self.env.clone()));
-                self.env = Environment::new_local(previous.clone());
-                let mut actual = match name {
-                    Some(n) => n.clone(),
-                    None => LocToken {
-                        token: Identifier("i".to_string()),
-                        loc: Self::get_loc_token_from_expr(iterable).loc,
-                    },
+                if arity(&method.params) != arity(params) {
+                    let loc = method.name.as_ref().map_or(class.name.loc, |n| n.loc);
+                    return Err(Error::new(loc, format!("Runtime Error: Method '{}' of class '{}' does not match '{}({})' declared by trait '{}'.", method.signature(), class.name.token, name, params.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "), t.name.token).as_str()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // variants without fields are values already, the others are called to build one
+    fn constructor(ty: &Rc<variant::Enum>, tag: usize) -> Value {
+        if ty.variants[tag].1.is_empty() {
+            Value::Variant(Rc::new(Variant { ty: ty.clone(), tag, values: Vec::new() }))
+        } else {
+            Value::Constructor(ty.clone(), tag)
+        }
+    }
+
+    // whether the value fits the case, binding the names it introduces into env
+    fn matches(&mut self, case: &Case, value: &Value, env: &Rc<RefCell<Environment>>) -> Result<bool, Error> {
+        match case {
+            Case::Wildcard(_) => Ok(true),
+            Case::Literal(token) => Ok(Value::from(token.token.clone()) == *value),
+            Case::Name(name) => match self.env.borrow().get(name) {
+                Ok(Value::Variant(variant)) if variant.values.is_empty() => Ok(matches!(value, Value::Variant(v) if *v == variant)),
+                Ok(Value::Constructor(ty, tag)) => Ok(matches!(value, Value::Variant(v) if Rc::ptr_eq(&v.ty, &ty) && v.tag == tag)),
+                _ => {
+                    env.borrow_mut().define(name, value.clone())?;
+                    Ok(true)
+                }
+            },
+            Case::Variant(name, fields) => {
+                let (ty, tag) = match self.env.borrow().get(name)? {
+                    Value::Constructor(ty, tag) => (ty, tag),
+                    Value::Variant(variant) => (variant.ty.clone(), variant.tag),
+                    _ => return Err(Error::new(name.loc, format!("Runtime Error: '{}' is not an enum variant.", name.token).as_str()))
                 };
-                self.env.define(&actual, Value::Number(range.start));
-                match self.env.get(&actual).unwrap() {
-                    Value::Number(_) => {}
-                    _ => return Err(Error::new(Self::get_loc_token_from_expr(iterable).loc, "Runtime Error: For loop variable must be a number.")),
-                }
-                /*
-                Rn this iterates over the range no matter what but if the variable is modified within it to be larger than the range then it doesnt care and keeps going
-                Either fix this or make i immutable
-                 */
-                while let Value::Number(i) = self.env.get(&actual).unwrap() {
-                    self.execute(body)?;
-                    if let Value::Number(i) = self.env.get(&actual).unwrap() {
-                        self.env.assign(actual.clone(), Value::Number(i + 1.0))?;
-                    } else {
-                        return Err(Error::new(Self::get_loc_token_from_expr(iterable).loc, "Runtime Error: For loop variable must be a number."));
+                if ty.variants[tag].1.len() != fields.len() {
+                    return Err(Error::new(name.loc, format!("Runtime Error: Variant '{}' has {} fields but the pattern has {}.", name.token, ty.variants[tag].1.len(), fields.len()).as_str()));
+                }
+                match value {
+                    Value::Variant(variant) if Rc::ptr_eq(&variant.ty, &ty) && variant.tag == tag => {
+                        for (field, value) in fields.iter().zip(&variant.values) {
+                            if !self.matches(field, value, env)? {
+                                return Ok(false);
+                            }
+                        }
+                        Ok(true)
+                    }
+                    _ => Ok(false)
+                }
+            }
+        }
+    }
+
+    // a method looked up on an instance, already bound to it
+    fn method(value: &Value, name: &str) -> Option<Value> {
+        match value {
+            Value::Instance(instance) => {
+                let method = instance.borrow().class.find_method(name)?;
+                Some(Value::Function(Rc::new(method.bind(instance))))
+            }
+            _ => None
+        }
+    }
+
+    // instances implement operators through special methods, tried in order as (method, swap operands, negate result)
+    fn evaluate_binary(&mut self, op: &LocToken, left: Value, right: Value) -> Result<Value, Error> {
+        if let Some(result) = self.overload(op, &left, &right)? {
+            return Ok(result);
+        }
+        match op.token {
+            Plus => {
+                match left {
+                    Value::Number(l) => {
+                        match right {
+                            Value::Number(r) => Ok(Value::Number(l + r)),
+                            Value::String(r) => Ok(Value::String(format!("{}{}", l, r))),
+                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot add 'Number' with anything but 'Number' or 'String' or an expression evaluating to it"))
+                        }
                     }
-                    if let Value::Number(new_i) = self.env.get(&actual).unwrap() {
-                        if new_i >= range.end {
-                            break;
+                    Value::String(l) => {
+                        match right {
+                            Value::Number(r) => Ok(Value::String(format!("{}{}", l, r))),
+                            Value::String(r) => Ok(Value::String(format!("{}{}", l, r))),
+                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot add 'String' with anything but 'String' or 'Number' or an expression evaluating to it"))
                         }
-                    } else {
-                        return Err(Error::new(Self::get_loc_token_from_expr(iterable).loc, "Runtime Error: For loop variable must be a number."));
                     }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot add anything to a non 'Number' or 'String' expression."))
                 }
-                self.execute(body)?;
-                self.env = previous.borrow().clone();
-                Ok(Value::Null)
             }
 
-            _ => {
-                Err(Error::new(Loc { line: 0, col: 0, idx: 0 }, "Runtime Error: Not Implemented."))
+            Minus => {
+                match left {
+                    Value::Number(l) => {
+                        match right {
+                            Value::Number(r) => Ok(Value::Number(l - r)),
+                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot subtract 'Number' with anything but 'Number' or an expression evaluating to it"))
+                        }
+                    }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot subtract anything from a non 'Number' expression."))
+                }
+            }
+
+            Star => {
+                match left {
+                    Value::Number(l) => {
+                        match right {
+                            Value::Number(r) => Ok(Value::Number(l * r)),
+                            Value::String(r) => Ok(Value::String(r.repeat(l.round() as usize))),
+                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot multiply 'Number' with anything but 'Number' or 'String' or an expression evaluating to it"))
+                        }
+                    }
+                    Value::String(l) => {
+                        match right {
+                            Value::Number(r) => Ok(Value::String(l.repeat(r.round() as usize))),
+                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot multiply 'String' with anything but 'Number' or an expression evaluating to it"))
+                        }
+                    }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot multiply anything with a non 'Number' or 'String' expression."))
+                }
+            }
+
+            Slash => {
+                match right {
+                    Value::Number(r) => {
+                        if r == 0.0 {
+                            Err(Error::new(op.loc, "Runtime Error: Division by zero."))
+                        } else {
+                            match left {
+                                Value::Number(l) => Ok(Value::Number(l / r)),
+                                _ => Err(Error::new(op.loc, "Runtime Error: Cannot divide anything but 'Number' or an expression evaluating to it"))
+                            }
+                        }
+                    }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot divide by anything but 'Number' or an expression evaluating to it"))
+                }
+            }
+
+            // floored, so the result takes the sign of the divisor and a == (a ~/ b) * b + a % b
+            Percent => {
+                match right {
+                    Value::Number(r) => {
+                        if r == 0.0 {
+                            Err(Error::new(op.loc, "Runtime Error: Division by zero."))
+                        } else {
+                            match left {
+                                Value::Number(l) => Ok(Value::Number(l - r * (l / r).floor())),
+                                _ => Err(Error::new(op.loc, "Runtime Error: Cannot take the remainder of anything but 'Number' or an expression evaluating to it"))
+                            }
+                        }
+                    }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot take the remainder by anything but 'Number' or an expression evaluating to it"))
+                }
+            }
+
+            TildeSlash => {
+                match right {
+                    Value::Number(r) => {
+                        if r == 0.0 {
+                            Err(Error::new(op.loc, "Runtime Error: Division by zero."))
+                        } else {
+                            match left {
+                                Value::Number(l) => Ok(Value::Number((l / r).floor())),
+                                _ => Err(Error::new(op.loc, "Runtime Error: Cannot divide anything but 'Number' or an expression evaluating to it"))
+                            }
+                        }
+                    }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot divide by anything but 'Number' or an expression evaluating to it"))
+                }
+            }
+
+            StarStar => {
+                match left {
+                    Value::Number(l) => {
+                        match right {
+                            Value::Number(r) => Ok(Value::Number(l.powf(r))),
+                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot raise 'Number' to anything but 'Number' or an expression evaluating to it"))
+                        }
+                    }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot raise a non 'Number' expression to a power."))
+                }
+            }
+
+            Ampersand => Ok(Value::Number((builtins::integer(&left, op)? & builtins::integer(&right, op)?) as f64)),
+            Pipe => Ok(Value::Number((builtins::integer(&left, op)? | builtins::integer(&right, op)?) as f64)),
+            Caret => Ok(Value::Number((builtins::integer(&left, op)? ^ builtins::integer(&right, op)?) as f64)),
+
+            LessLess | GreaterGreater => {
+                let l = builtins::integer(&left, op)?;
+                let r = builtins::integer(&right, op)?;
+                if !(0..64).contains(&r) {
+                    return Err(Error::new(op.loc, format!("Runtime Error: Shift amount must be between 0 and 63, but found '{}'.", r).as_str()));
+                }
+                Ok(Value::Number(if op.token == LessLess { l << r } else { l >> r } as f64))
             }
+
+            Range => {
+                match left {
+                    Value::Number(l) => {
+                        match right {
+                            Value::Number(r) => Ok(Value::Range(l, r)),
+                            _ => Err(Error::new(op.loc, "Runtime Error: Cannot create a range with anything but 'Number' or an expression evaluating to it"))
+                        }
+                    }
+                    _ => Err(Error::new(op.loc, "Runtime Error: Cannot create a range with anything but 'Number' or an expression evaluating to it"))
+                }
+            }
+
+            EqualEqual | BangEqual => {
+                let comparison = self.equal(op, &left, &right)?;
+                Ok(Value::Boolean(if op.token == EqualEqual { comparison } else { !comparison }))
+            }
+
+            Greater | GreaterEqual | Less | LessEqual => {
+                let ordering = Self::order(op, &left, &right)?;
+                Ok(Value::Boolean(match op.token {
+                    Greater => ordering.is_gt(),
+                    GreaterEqual => ordering.is_ge(),
+                    Less => ordering.is_lt(),
+                    _ => ordering.is_le(),
+                }))
+            }
+
+            Is => Ok(Value::Boolean(match (&left, &right) {
+                (Value::Instance(instance), Value::Trait(t)) => instance.borrow().class.implements(t),
+                (Value::Instance(instance), Value::Class(class)) => instance.borrow().class.inherits(class),
+                (Value::Class(class), Value::Trait(t)) => class.implements(t),
+                (Value::Variant(variant), Value::Enum(ty)) => Rc::ptr_eq(&variant.ty, ty),
+                (_, Value::Trait(_) | Value::Class(_) | Value::Enum(_)) => false,
+                _ => return Err(Error::new(op.loc, "Runtime Error: The right side of 'is' must be a trait, class or enum."))
+            })),
+
+            Identifier(_) => {
+                let function = self.env.borrow().get(op)?;
+                self.call(function, op, vec![left, right])
+            }
+
+            _ => Err(Error::new(op.loc, "Runtime Error: Invalid binary operator"))
+        }
+    }
+
+    // numbers and strings order naturally, lists and tuples element by element, anything else or a mix of types cannot be ordered
+    fn order(op: &LocToken, left: &Value, right: &Value) -> Result<std::cmp::Ordering, Error> {
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => l.partial_cmp(r).ok_or_else(|| Error::new(op.loc, "Runtime Error: Cannot order 'NaN'.")),
+            (Value::String(l), Value::String(r)) => Ok(l.cmp(r)),
+            (Value::List(l), Value::List(r)) => Self::order_items(op, &l.borrow(), &r.borrow()),
+            (Value::Tuple(l), Value::Tuple(r)) => Self::order_items(op, l, r),
+            (l, r) if l.type_name() == r.type_name() => Err(Error::new(op.loc, format!("Runtime Error: Cannot order values of type '{}'.", l.type_name()).as_str())),
+            (l, r) => Err(Error::new(op.loc, format!("Runtime Error: Cannot compare '{}' with '{}'.", l.type_name(), r.type_name()).as_str()))
         }
     }
 
-    fn get_loc_token_from_expr(expr: &Expr) -> LocToken {
+    fn order_items(op: &LocToken, left: &[Value], right: &[Value]) -> Result<std::cmp::Ordering, Error> {
+        for (l, r) in left.iter().zip(right) {
+            match Self::order(op, l, r)? {
+                std::cmp::Ordering::Equal => {}
+                ordering => return Ok(ordering),
+            }
+        }
+        Ok(left.len().cmp(&right.len()))
+    }
+
+    // a number on the left falls back to the right operand's reflected method, like 'rsub' for 2 - v,
+    // and arithmetic on an instance that neither side handles is an error rather than the built-in one
+    fn overload(&mut self, op: &LocToken, left: &Value, right: &Value) -> Result<Option<Value>, Error> {
+        let candidates: &[(&str, bool, bool)] = match op.token {
+            Plus => &[("add", false, false), ("radd", true, false)],
+            Minus => &[("sub", false, false), ("rsub", true, false)],
+            Star => &[("mul", false, false), ("rmul", true, false)],
+            Slash => &[("div", false, false), ("rdiv", true, false)],
+            Percent => &[("mod", false, false), ("rmod", true, false)],
+            StarStar => &[("pow", false, false), ("rpow", true, false)],
+            Less => &[("lt", false, false), ("gt", true, false)],
+            Greater => &[("gt", false, false), ("lt", true, false)],
+            LessEqual => &[("le", false, false), ("ge", true, false), ("lt", true, true), ("gt", false, true)],
+            GreaterEqual => &[("ge", false, false), ("le", true, false), ("lt", false, true), ("gt", true, true)],
+            _ => return Ok(None)
+        };
+        for (name, swap, negate) in candidates {
+            let (receiver, argument) = if *swap { (right, left) } else { (left, right) };
+            if let Some(method) = Self::method(receiver, name) {
+                let result = self.call(method, op, vec![argument.clone()])?;
+                return Ok(Some(if *negate { Value::Boolean(!self.truthy(&result, op.loc)?) } else { result }));
+            }
+        }
+        let arithmetic = matches!(op.token, Plus | Minus | Star | Slash | Percent | StarStar);
+        if arithmetic && (matches!(left, Value::Instance(_)) || matches!(right, Value::Instance(_))) {
+            return Err(Error::new(op.loc, format!("Runtime Error: Cannot apply '{}' to '{}' and '{}'.", op.token, builtins::type_name(left), builtins::type_name(right)).as_str()));
+        }
+        Ok(None)
+    }
+
+    // one rule for == at every level, so values inside tuples, lists, maps and variants compare the way they do on their own;
+    // containers compare by contents, functions, classes and instances without an 'eq' method by identity
+    fn equal(&mut self, op: &LocToken, left: &Value, right: &Value) -> Result<bool, Error> {
+        for (receiver, argument) in [(left, right), (right, left)] {
+            if let Some(method) = Self::method(receiver, "eq") {
+                let result = self.call(method, op, vec![argument.clone()])?;
+                return self.truthy(&result, op.loc);
+            }
+        }
+        Ok(match (left, right) {
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::Range(l_start, l_end), Value::Range(r_start, r_end)) => l_start == r_start && l_end == r_end,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Boolean(l), Value::Boolean(r)) => l == r,
+            (Value::Null, Value::Null) => true,
+            (Value::Error(l, l_loc), Value::Error(r, r_loc)) => l == r && l_loc == r_loc,
+            (Value::List(l), Value::List(r)) => {
+                if Rc::ptr_eq(l, r) {
+                    return Ok(true);
+                }
+                let (l, r) = (l.borrow().clone(), r.borrow().clone());
+                self.equal_items(op, &l, &r)?
+            }
+            (Value::Tuple(l), Value::Tuple(r)) => self.equal_items(op, l, r)?,
+            (Value::Map(l), Value::Map(r)) => {
+                if Rc::ptr_eq(l, r) {
+                    return Ok(true);
+                }
+                let (l, r) = (l.borrow().clone(), r.borrow().clone());
+                if !l.keys().eq(r.keys()) {
+                    return Ok(false);
+                }
+                let (l, r): (Vec<Value>, Vec<Value>) = (l.into_values().collect(), r.into_values().collect());
+                self.equal_items(op, &l, &r)?
+            }
+            (Value::Variant(l), Value::Variant(r)) => Rc::ptr_eq(&l.ty, &r.ty) && l.tag == r.tag && self.equal_items(op, &l.values, &r.values)?,
+            (Value::Native(l), Value::Native(r)) => l.name == r.name,
+            (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Trait(l), Value::Trait(r)) => Rc::ptr_eq(l, r),
+            (Value::Generator(l), Value::Generator(r)) => Rc::ptr_eq(l, r),
+            (Value::Task(l), Value::Task(r)) => Rc::ptr_eq(l, r),
+            (Value::Enum(l), Value::Enum(r)) => Rc::ptr_eq(l, r),
+            (Value::Constructor(l, l_tag), Value::Constructor(r, r_tag)) => Rc::ptr_eq(l, r) && l_tag == r_tag,
+            _ => false
+        })
+    }
+
+    fn equal_items(&mut self, op: &LocToken, left: &[Value], right: &[Value]) -> Result<bool, Error> {
+        if left.len() != right.len() {
+            return Ok(false);
+        }
+        for (l, r) in left.iter().zip(right) {
+            if !self.equal(op, l, r)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // what log prints, instances can customise it with a 'str' method, which also applies inside containers
+    pub fn stringify(&mut self, value: &Value, loc: &LocToken) -> Result<std::string::String, Error> {
+        if let Some(method) = Self::method(value, "str") {
+            return Ok(self.call(method, loc, Vec::new())?.to_string());
+        }
+        Ok(match value {
+            Value::List(items) => {
+                let items = items.borrow().clone();
+                format!("[{}]", self.reprs(&items, loc)?)
+            }
+            Value::Tuple(items) if items.len() == 1 => format!("({},)", self.repr(&items[0], loc)?),
+            Value::Tuple(items) => format!("({})", self.reprs(items, loc)?),
+            Value::Map(entries) => {
+                let entries = entries.borrow().clone();
+                let mut parts = Vec::new();
+                for (key, value) in &entries {
+                    parts.push(format!("{}: {}", key, self.repr(value, loc)?));
+                }
+                format!("{{{}}}", parts.join(", "))
+            }
+            Value::Variant(variant) if !variant.values.is_empty() => format!("{}({})", variant.name(), self.reprs(&variant.values, loc)?),
+            value => value.to_string()
+        })
+    }
+
+    // strings inside containers keep their quotes
+    fn repr(&mut self, value: &Value, loc: &LocToken) -> Result<std::string::String, Error> {
+        match value {
+            Value::String(s) => Ok(Value::quote(s)),
+            value => self.stringify(value, loc)
+        }
+    }
+
+    fn reprs(&mut self, values: &[Value], loc: &LocToken) -> Result<std::string::String, Error> {
+        let mut parts = Vec::new();
+        for value in values {
+            parts.push(self.repr(value, loc)?);
+        }
+        Ok(parts.join(", "))
+    }
+
+    // the value of an access chain, None once a ?. in it met null, which skips the rest of the chain
+    fn access(&mut self, expr: &Expr) -> Result<Option<Value>, Error> {
+        let object = match expr {
+            Expr::Call(object, _, _, _) | Expr::Index(object, _, _) | Expr::Get(object, _) | Expr::SafeGet(object, _) => match self.access(object)? {
+                Some(Value::Null) if matches!(expr, Expr::SafeGet(_, _)) => return Ok(None),
+                Some(object) => object,
+                None => return Ok(None)
+            },
+            expr => return self.evaluate(expr).map(Some)
+        };
+        match expr {
+            Expr::Call(_, paren, args, named) => {
+                let (arguments, named) = self.arguments(args, named)?;
+                self.call_with_named(object, paren, arguments, named).map(Some)
+            }
+            Expr::Index(_, bracket, index) => self.index(object, bracket, index).map(Some),
+            Expr::Get(_, name) | Expr::SafeGet(_, name) => Self::get_field(&object, name).map(Some),
+            _ => unreachable!("only access chains get here")
+        }
+    }
+
+    fn index(&mut self, object: Value, bracket: &LocToken, index: &Expr) -> Result<Value, Error> {
+        let index = self.evaluate(index)?;
+        match object {
+            Value::List(items) => {
+                let i = builtins::integer(&index, bracket)?;
+                let items = items.borrow();
+                match usize::try_from(i).ok().and_then(|i| items.get(i)) {
+                    Some(v) => Ok(v.clone()),
+                    None => Err(Error::new(bracket.loc, format!("Runtime Error: Index {} is out of bounds for a list of length {}.", i, items.len()).as_str()))
+                }
+            }
+            Value::Tuple(items) => {
+                let i = builtins::integer(&index, bracket)?;
+                match usize::try_from(i).ok().and_then(|i| items.get(i)) {
+                    Some(v) => Ok(v.clone()),
+                    None => Err(Error::new(bracket.loc, format!("Runtime Error: Index {} is out of bounds for a tuple of length {}.", i, items.len()).as_str()))
+                }
+            }
+            Value::Map(entries) => match index {
+                Value::String(key) => match entries.borrow().get(&key) {
+                    Some(v) => Ok(v.clone()),
+                    None => Err(Error::new(bracket.loc, format!("Runtime Error: Undefined field '{}'.", key).as_str()))
+                },
+                _ => Err(Error::new(bracket.loc, "Runtime Error: Map keys must be 'String'."))
+            },
+            _ => Err(Error::new(bracket.loc, "Runtime Error: Can only index into a 'List', 'Tuple' or 'Map'."))
+        }
+    }
+
+    fn set_field(object: &Value, name: &LocToken, value: Value) -> Result<Value, Error> {
+        match object {
+            Value::Map(entries) => {
+                entries.borrow_mut().insert(name.token.to_string(), value.clone());
+                Ok(value)
+            }
+            Value::Instance(instance) => {
+                instance.borrow_mut().fields.insert(name.token.to_string(), value.clone());
+                Ok(value)
+            }
+            _ => Err(Error::new(name.loc, "Runtime Error: Only maps and instances have fields."))
+        }
+    }
+
+    fn get_field(object: &Value, name: &LocToken) -> Result<Value, Error> {
+        match object {
+            Value::Error(message, loc) => match name.token.to_string().as_str() {
+                "message" => Ok(Value::String(message.clone())),
+                "line" => Ok(Value::Number(loc.line as f64)),
+                "col" => Ok(Value::Number(loc.col as f64)),
+                _ => Err(Error::new(name.loc, format!("Runtime Error: Errors only have the fields 'message', 'line' and 'col', not '{}'.", name.token).as_str()))
+            },
+            Value::Map(entries) => match entries.borrow().get(&name.token.to_string()) {
+                Some(v) => Ok(v.clone()),
+                None => Err(Error::new(name.loc, format!("Runtime Error: Undefined field '{}'.", name.token).as_str()))
+            },
+            Value::Variant(variant) => match variant.field(&name.token.to_string()) {
+                Some(v) => Ok(v),
+                None => Err(Error::new(name.loc, format!("Runtime Error: Variant '{}' has no field '{}'.", variant.name(), name.token).as_str()))
+            },
+            Value::Enum(ty) => match ty.tag(&name.token.to_string()) {
+                Some(tag) => Ok(Self::constructor(ty, tag)),
+                None => Err(Error::new(name.loc, format!("Runtime Error: Enum '{}' has no variant '{}'.", ty.name.token, name.token).as_str()))
+            },
+            Value::Instance(instance) => {
+                let instance_ref = instance.borrow();
+                match instance_ref.fields.get(&name.token.to_string()) {
+                    Some(v) => Ok(v.clone()),
+                    None => match instance_ref.class.find_method(&name.token.to_string()) {
+                        Some(method) => Ok(Value::Function(Rc::new(method.bind(instance)))),
+                        None => Err(Error::new(name.loc, format!("Runtime Error: Undefined property '{}' on '{}'.", name.token, instance_ref.class.name.token).as_str()))
+                    }
+                }
+            }
+            _ => Err(Error::new(name.loc, "Runtime Error: Only maps and instances have fields."))
+        }
+    }
+
+    // with a single value on the right, several targets unpack it as if they were written [a, b] = value
+    fn destructure(&mut self, patterns: &[Pattern], loc: &LocToken, values: &[Expr], binding: Binding) -> Result<(), Error> {
+        let mut evaluated = Vec::new();
+        for value in values {
+            evaluated.push(self.evaluate(value)?);
+        }
+        self.bind_values(patterns, loc, evaluated, binding)
+    }
+
+    fn bind_values(&mut self, patterns: &[Pattern], loc: &LocToken, mut evaluated: Vec<Value>, binding: Binding) -> Result<(), Error> {
+        if evaluated.is_empty() {
+            for name in patterns.iter().flat_map(Pattern::names) {
+                self.env.borrow_mut().declare(name)?;
+            }
+            return Ok(());
+        }
+        if patterns.len() != evaluated.len() {
+            if evaluated.len() != 1 {
+                return Err(Error::new(loc.loc, format!("Runtime Error: Cannot assign {} values to {} targets.", evaluated.len(), patterns.len()).as_str()));
+            }
+            evaluated = Self::unpack(&evaluated[0], patterns.len(), loc)?;
+        }
+        for (pattern, value) in patterns.iter().zip(evaluated) {
+            self.bind_pattern(pattern, value, binding)?;
+        }
+        Ok(())
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern, value: Value, binding: Binding) -> Result<(), Error> {
+        match pattern {
+            Pattern::Name(name, _) => match binding {
+                Binding::Variable => self.env.borrow_mut().define(name, value)?,
+                Binding::Constant => self.env.borrow_mut().define_constant(name, value)?,
+                Binding::Assign => self.env.borrow_mut().assign(name.clone(), value)?,
+            },
+            Pattern::List(bracket, items) => {
+                for (item, value) in items.iter().zip(Self::unpack(&value, items.len(), bracket)?) {
+                    self.bind_pattern(item, value, binding)?;
+                }
+            }
+            Pattern::Map(_, entries) => {
+                for (key, item) in entries {
+                    self.bind_pattern(item, Self::get_field(&value, key)?, binding)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn unpack(value: &Value, count: usize, loc: &LocToken) -> Result<Vec<Value>, Error> {
+        match value {
+            Value::List(items) if items.borrow().len() == count => Ok(items.borrow().clone()),
+            Value::List(items) => Err(Error::new(loc.loc, format!("Runtime Error: Cannot destructure a list of length {} into {} targets.", items.borrow().len(), count).as_str())),
+            Value::Tuple(items) if items.len() == count => Ok(items.to_vec()),
+            Value::Tuple(items) => Err(Error::new(loc.loc, format!("Runtime Error: Cannot destructure a tuple of length {} into {} targets.", items.len(), count).as_str())),
+            _ => Err(Error::new(loc.loc, format!("Runtime Error: Cannot destructure '{}' into {} targets, expected a list or tuple.", value.repr(), count).as_str()))
+        }
+    }
+
+    // positional arguments are bound first, then named ones, and the defaults of whatever is left are evaluated in the callee's environment
+    fn call_function(&mut self, mut function: Rc<Function>, paren: &LocToken, mut arguments: Vec<Value>, mut named: Vec<(LocToken, Value)>) -> Result<Value, Error> {
+        let mut paren = paren.clone();
+        // a tail call comes back here instead of nesting, so the loop runs it in this frame
+        loop {
+            let env = Rc::new(RefCell::new(Environment::new_local(function.closure.clone())));
+            let previous = std::mem::replace(&mut self.env, env.clone());
+            let bound = self.bind_arguments(&function, &paren, arguments, named);
+            self.env = previous;
+            bound?;
+            match function.kind {
+                Kind::Generator => return Ok(Value::Generator(Rc::new(RefCell::new(Generator::new(function, env))))),
+                Kind::Async => return Ok(Value::Task(self.scheduler.start(Generator::new(function, env)))),
+                Kind::Function => {}
+            }
+            let tail_calls = std::mem::replace(&mut self.tail_calls, true);
+            let out = self.execute_block(&function.body, env);
+            self.tail_calls = tail_calls;
+            match out {
+                Ok(_) => return Ok(Value::Null),
+                Err(mut e) => match e.unwind.take() {
+                    Some(Unwind::Return(value)) => return Ok(value),
+                    Some(Unwind::TailCall(call)) => {
+                        (function, paren, (arguments, named)) = *call;
+                        if let Some(top) = self.calls.last_mut() {
+                            *top = (function.clone(), paren.loc);
+                        }
+                    }
+                    unwind => {
+                        e.unwind = unwind;
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn bind_arguments(&mut self, function: &Function, paren: &LocToken, arguments: Vec<Value>, named: Vec<(LocToken, Value)>) -> Result<(), Error> {
+        let fixed: Vec<&Param> = function.params.iter().filter(|p| !p.variadic).collect();
+        let variadic = function.params.iter().find(|p| p.variadic);
+        let mut values: Vec<Option<Value>> = vec![None; fixed.len()];
+        let mut rest = Vec::new();
+        let count = arguments.len();
+        for (i, argument) in arguments.into_iter().enumerate() {
+            if i < fixed.len() {
+                values[i] = Some(argument);
+            } else if variadic.is_some() {
+                rest.push(argument);
+            } else {
+                return Err(Error::new(paren.loc, format!("Runtime Error: Expected at most {} arguments but got {} calling '{}'.", fixed.len(), count, function.signature()).as_str()));
+            }
+        }
+        for (name, value) in named {
+            match fixed.iter().position(|p| p.name.token.to_string() == name.token.to_string()) {
+                Some(i) if values[i].is_some() => return Err(Error::new(name.loc, format!("Runtime Error: Argument '{}' was given more than once calling '{}'.", name.token, function.signature()).as_str())),
+                Some(i) => values[i] = Some(value),
+                None => return Err(Error::new(name.loc, format!("Runtime Error: Unknown argument '{}' calling '{}'.", name.token, function.signature()).as_str()))
+            }
+        }
+        for (param, value) in fixed.iter().zip(values) {
+            let value = match (value, &param.default) {
+                (Some(v), _) => v,
+                (None, Some(default)) => self.evaluate(default)?,
+                (None, None) => return Err(Error::new(paren.loc, format!("Runtime Error: Missing argument '{}' calling '{}'.", param.name.token, function.signature()).as_str()))
+            };
+            self.env.borrow_mut().define(&param.name, value)?;
+        }
+        if let Some(param) = variadic {
+            self.env.borrow_mut().define(&param.name, Value::List(Rc::new(RefCell::new(rest))))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_loc_token_from_expr(expr: &Expr) -> LocToken {
         match expr {
             Expr::Literal(token) => token.clone(),
             Expr::Grouping(e) => Self::get_loc_token_from_expr(e),
@@ -343,13 +1306,34 @@ impl Interpreter {
             Expr::Literal(token) => token.clone(),
             Expr::Unary(token, _) => token.clone(),
             Expr::Get(_, token) => token.clone(),
+            Expr::SafeGet(_, token) => token.clone(),
             Expr::Set(_, token, _) => token.clone(),
             Expr::Logical(_, token, _) => token.clone(),
-            Expr::Super(token) => token.clone(),
+            Expr::Ternary(_, question, _, _) => question.clone(),
+            Expr::Super(token, _) => token.clone(),
+            Expr::Match(keyword, _, _) => keyword.clone(),
+            Expr::Await(keyword, _) => keyword.clone(),
             Expr::This(token) => token.clone(),
+            Expr::Comprehension(bracket, _) => bracket.clone(),
             Expr::Variable(token) => token.clone(),
             Expr::Binary(_, token, _) => token.clone(),
-            Expr::Call(_, _) => panic!()
+            Expr::Comparison(_, rest) => rest[0].0.clone(),
+            Expr::Call(_, paren, _, _) => paren.clone(),
+            Expr::Index(_, bracket, _) => bracket.clone(),
+            Expr::List(bracket, _) => bracket.clone(),
+            Expr::Map(brace, _) => brace.clone(),
+            Expr::Tuple(paren, _) => paren.clone(),
+            Expr::Lambda(keyword, _, _, _) => keyword.clone()
+        }
+    }
+
+    pub fn get_loc_token_from_stmt(stmt: &Stmt) -> Option<LocToken> {
+        match stmt {
+            Stmt::Block(stmts) | Stmt::Try(stmts, _, _) => stmts.first().and_then(Self::get_loc_token_from_stmt),
+            Stmt::Class(name, _, _, _) | Stmt::Trait(name, _, _) | Stmt::Function(name, _, _, _, _) | Stmt::Infix(name, _, _) | Stmt::Enum(name, _) => Some(name.clone()),
+            Stmt::Return(keyword, _) | Stmt::Yield(keyword, _) | Stmt::Declaration(keyword, _, _) | Stmt::Throw(keyword, _) => Some(keyword.clone()),
+            Stmt::Assign(_, op, _) => Some(op.clone()),
+            Stmt::Expression(e) | Stmt::Log(e) | Stmt::If(e, _, _) | Stmt::While(e, _) | Stmt::For(_, e, _) => Some(Self::get_loc_token_from_expr(e)),
         }
     }
-}
\ No newline at end of file
+}
@@ -2,6 +2,7 @@
 
 use std::env;
 use std::process;
+use std::thread;
 use rustyline::error::ReadlineError;
 
 mod icps;
@@ -11,24 +12,60 @@ mod ast;
 mod parser;
 mod environment;
 mod interpreter;
+mod builtins;
+mod function;
+mod class;
+mod variant;
+mod generator;
+mod scheduler;
+mod checker;
+
+use interpreter::Interpreter;
 
 fn main() -> Result<(), ReadlineError> {
-    let mut interpreter = interpreter::Interpreter::new();
     let args: Vec<String> = env::args().collect();
 
-    let non_option_args = args.iter().skip(1).filter(|arg| !arg.starts_with('-')).count();
+    let max_depth = match args.iter().find_map(|arg| arg.strip_prefix("--max-depth=")) {
+        Some(depth) => match depth.parse() {
+            Ok(depth) if depth <= interpreter::DEPTH_LIMIT => depth,
+            _ => {
+                println!("Usage: --max-depth=<number> with a number up to {}.", interpreter::DEPTH_LIMIT);
+                process::exit(64);
+            }
+        },
+        None => interpreter::MAX_DEPTH,
+    };
+
+    let non_option_args: Vec<String> = args.iter().skip(1).filter(|arg| !arg.starts_with('-')).cloned().collect();
 
-    if non_option_args > 1 {
+    if non_option_args.len() > 1 {
         println!("Usage: icps <file> [OPTIONS] or icps [OPTIONS] for REPL.");
         process::exit(64);
     }
 
-    if args.len() > 1 && !args[1].starts_with('-') {
-        icps::run_file(&args[1], &mut interpreter);
-    } else {
-        icps::run_prompt(&mut interpreter)?;
-    }
+    // the interpreter recurses on the native stack, so it runs on a thread with room for max_depth levels
+    let runner = thread::Builder::new().stack_size(Interpreter::stack_size(max_depth)).spawn(move || {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_max_depth(max_depth);
+        interpreter.set_strict_bool(args.iter().any(|arg| arg == "--strict-bool"));
 
-    Ok(())
-}
+        if args.iter().any(|arg| arg == "--virtual-clock") {
+            interpreter.scheduler.use_virtual_clock();
+        }
 
+        if let Some(path) = non_option_args.first() {
+            icps::run_file(path, &mut interpreter);
+            Ok(())
+        } else {
+            icps::run_prompt(&mut interpreter)
+        }
+    });
+
+    match runner {
+        Ok(runner) => runner.join().unwrap_or_else(|_| process::exit(70)),
+        Err(e) => {
+            println!("Usage: --max-depth=<number>, the system could not give {} levels a stack ({}).", max_depth, e);
+            process::exit(64);
+        }
+    }
+}
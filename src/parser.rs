@@ -1,8 +1,11 @@
+use std::collections::HashMap;
 use std::process;
+use lazy_static::lazy_static;
 use crate::scanner::LocToken;
 use crate::ast::*;
 use crate::icps;
 use icps::*;
+use crate::interpreter::Interpreter;
 use crate::token::Token::{self, *};
 
 macro_rules! cmp {
@@ -18,17 +21,98 @@ macro_rules! cmp {
     }};
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Operator {
+    pub precedence: u8,
+    pub assoc: Assoc,
+}
+
+pub type Operators = HashMap<std::string::String, Operator>;
+
+// the precedences scripts name in 'infix', higher binds tighter
+const LOWEST: u8 = 1;
+const RANGE: u8 = 8;
+const UNARY: u8 = 12;
+const POWER: u8 = 13;
+
+// the parser works on twice the scale scripts see, so 'xor' and 'not' fit between 'or', 'and' and '|' without renumbering the levels 'infix' uses
+const fn level(precedence: u8) -> u8 {
+    precedence * 2
+}
+
+const XOR: u8 = level(LOWEST) + 1;
+const NOT: u8 = level(2) + 1;
+
+lazy_static! {
+    static ref OPERATORS: Operators = {
+        let mut m = HashMap::new();
+        let mut add = |tokens: &[Token], precedence: u8, assoc: Assoc| {
+            for token in tokens {
+                m.insert(token.to_string(), Operator { precedence, assoc });
+            }
+        };
+        add(&[Or, QuestionQuestion, PipeGreater], level(LOWEST), Assoc::Left);
+        add(&[Xor], XOR, Assoc::Left);
+        add(&[And], level(2), Assoc::Left);
+        // NOT sits here, so not a == b is not (a == b) and not a and b is (not a) and b
+        add(&[Pipe], level(3), Assoc::Left);
+        add(&[Caret], level(4), Assoc::Left);
+        add(&[Ampersand], level(5), Assoc::Left);
+        add(&[EqualEqual, BangEqual], level(6), Assoc::Left);
+        add(&[Greater, GreaterEqual, Less, LessEqual, Is], level(7), Assoc::Left);
+        add(&[Range], level(RANGE), Assoc::Left);
+        add(&[LessLess, GreaterGreater], level(9), Assoc::Left);
+        add(&[Plus, Minus], level(10), Assoc::Left);
+        add(&[Star, Slash, Percent, TildeSlash], level(11), Assoc::Left);
+        // UNARY sits here, so -2 ** 2 is -(2 ** 2)
+        add(&[StarStar], level(POWER), Assoc::Right);
+        m
+    };
+}
+
 pub struct Parser<'a> {
     tokens: &'a Vec<LocToken>,
+    operators: &'a mut Operators,
     cur: usize,
+    function_depth: usize,
+    // one entry per open bracket or block, true where newlines are insignificant
+    groups: Vec<bool>,
+    // one entry per enclosing class, true when it has a superclass
+    classes: Vec<bool>,
+    in_async: bool,
+    // nesting is limited like the interpreter's, so deep brackets are an error rather than a native stack overflow
+    depth: usize,
+    max_depth: usize,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a Vec<LocToken>) -> Parser<'a> {
+    pub fn new(tokens: &'a Vec<LocToken>, operators: &'a mut Operators, max_depth: usize) -> Parser<'a> {
         Parser {
             tokens,
+            operators,
             cur: 0,
+            function_depth: 0,
+            groups: Vec::new(),
+            classes: Vec::new(),
+            in_async: false,
+            depth: 0,
+            max_depth,
+        }
+    }
+
+    fn nest(&mut self) -> Result<(), Error> {
+        if self.depth >= self.max_depth {
+            let token = if matches!(self.previous().token, LeftParen | LeftBracket | LeftBrace) { self.previous() } else { self.peek() };
+            return Err(Error::new(token.loc, format!("Nesting went deeper than {}.", self.max_depth).as_str()));
         }
+        self.depth += 1;
+        Ok(())
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, Error> {
@@ -47,7 +131,7 @@ impl<'a> Parser<'a> {
 
     fn advance(&mut self) -> LocToken {
         if !self.is_at_end() {
-            self.cur += 1;
+            self.cur = self.position() + 1;
         }
         self.previous()
     }
@@ -56,11 +140,27 @@ impl<'a> Parser<'a> {
         self.peek().token == Eof
     }
 
+    // the index of the next token, stepping over newlines while inside brackets
+    fn position(&self) -> usize {
+        let mut cur = self.cur;
+        if self.groups.last() == Some(&true) {
+            while cur < self.tokens.len() && self.tokens[cur].token == Newline {
+                cur += 1;
+            }
+        }
+        cur
+    }
+
     fn peek(&self) -> LocToken {
-        if self.cur >= self.tokens.len() {
+        let cur = self.position();
+        if cur >= self.tokens.len() {
             process::exit(0);
         }
-        self.tokens[self.cur].clone()
+        self.tokens[cur].clone()
+    }
+
+    fn skip_newlines(&mut self) {
+        while cmp!(*self, Newline) {}
     }
 
     fn previous(&self) -> LocToken {
@@ -83,8 +183,9 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // a closing brace also ends a statement, so one-line blocks like { return x } work
     fn end_statement_if_not_else(&mut self) -> Result<(), Error> {
-        if cmp!(*self, Semicolon, Newline, Eof) || self.peek().token == Else {
+        if cmp!(*self, Semicolon, Newline, Eof) || self.peek().token == Else || self.check(RightBrace) {
             Ok(())
         } else {
             Err(Error::new(self.peek().loc, "Expected ';' or newline after statement."))
@@ -92,7 +193,7 @@ impl<'a> Parser<'a> {
     }
 
     fn end_statement(&mut self) -> Result<(), Error> {
-        if cmp!(*self, Semicolon, Newline, Eof) {
+        if cmp!(*self, Semicolon, Newline, Eof) || self.check(RightBrace) {
             Ok(())
         } else {
             Err(Error::new(self.peek().loc, "Expected ';' or newline after statement."))
@@ -100,8 +201,23 @@ impl<'a> Parser<'a> {
     }
 
     fn declaration(&mut self) -> Result<Stmt, Error> {
-        if cmp!(self, Var) {
+        if self.check(Var) || self.check(Let) || self.check(Const) {
+            self.advance();
             self.variable()
+        } else if self.check(Fn) && matches!(self.tokens[self.position() + 1].token, Identifier(_)) {
+            self.advance();
+            self.function(false)
+        } else if cmp!(*self, Async) {
+            self.consume(Fn)?;
+            self.function(true)
+        } else if cmp!(*self, Infix) {
+            self.infix()
+        } else if cmp!(*self, Class) {
+            self.class()
+        } else if cmp!(*self, Enum) {
+            self.enumeration()
+        } else if cmp!(*self, Trait) {
+            self.trait_declaration()
         } else {
             self.statement()
         }
@@ -116,6 +232,14 @@ impl<'a> Parser<'a> {
             self.while_loop()
         } else if cmp!(*self, For) {
             self.for_loop()
+        } else if cmp!(*self, Return) {
+            self.return_statement()
+        } else if cmp!(*self, Throw) {
+            self.throw_statement()
+        } else if cmp!(*self, Yield) {
+            self.yield_statement()
+        } else if cmp!(*self, Try) {
+            self.try_statement()
         } else if cmp!(*self, LeftBrace) {
             Ok(Stmt::Block(self.block()?))
         } else {
@@ -152,47 +276,483 @@ impl<'a> Parser<'a> {
             }
             _ => None
         };
-        let iterable = Box::new(self.range()?);
+        if var.is_some() {
+            cmp!(*self, In);
+        }
+        let iterable = Box::new(self.binary(level(RANGE))?);
         cmp!(*self, Newline);
         let body = Box::new(self.statement()?);
         Ok(Stmt::For(var, iterable, body))
     }
 
+    // the clauses nest left to right around the yield, so later clauses run inside earlier ones
+    fn comprehension(&mut self, bracket: LocToken, item: Stmt) -> Result<Expr, Error> {
+        let mut clauses = Vec::new();
+        while cmp!(*self, For) {
+            let name = self.consume(Identifier("".to_string()))?;
+            self.consume(In)?;
+            clauses.push((Some(name), self.expression()?));
+            while cmp!(*self, If) {
+                clauses.push((None, self.expression()?));
+            }
+        }
+        let body = clauses.into_iter().rev().fold(item, |body, (name, expr)| match name {
+            Some(name) => Stmt::For(Some(name), Box::new(expr), Box::new(body)),
+            None => Stmt::If(Box::new(expr), Box::new(body), None),
+        });
+        Ok(Expr::Comprehension(bracket, vec![body]))
+    }
+
+    fn map_comprehension(&mut self, brace: LocToken, key: Expr, value: Expr) -> Result<Expr, Error> {
+        if !self.check(For) {
+            return Err(Error::new(self.peek().loc, "Expected 'for' after a computed map key."));
+        }
+        let entry = Expr::Tuple(brace.clone(), vec![key, value]);
+        self.comprehension(brace.clone(), Stmt::Yield(brace, Box::new(entry)))
+    }
+
     fn block(&mut self) -> Result<Vec<Stmt>, Error> {
+        self.nest()?;
+        let statements = self.block_statements();
+        self.depth -= 1;
+        statements
+    }
+
+    fn block_statements(&mut self) -> Result<Vec<Stmt>, Error> {
         let mut statements: Vec<Stmt> = Vec::new();
+        self.groups.push(false);
 
         while self.check(Newline) {
             self.advance();
         }
 
         while !self.check(RightBrace) && !self.is_at_end() {
+            if cmp!(*self, Semicolon, Newline) {
+                continue;
+            }
             statements.push(self.declaration()?)
         }
 
         self.consume(RightBrace)?;
+        self.groups.pop();
         Ok(statements)
     }
 
     fn variable(&mut self) -> Result<Stmt, Error> {
-        let name = self.consume(Identifier("".to_string()))?;
-        let mut initializer = None;
+        let keyword = self.previous();
+        let mut patterns = vec![self.annotated_pattern()?];
+        while cmp!(*self, Comma) {
+            self.skip_newlines();
+            patterns.push(self.annotated_pattern()?);
+        }
+        Self::check_duplicates(&patterns)?;
+        let mut values = Vec::new();
 
         if cmp!(*self, Equal) {
-            initializer = Some(Box::new(self.expression()?));
+            values = self.expression_list()?;
+        } else if keyword.token != Var {
+            return Err(Error::new(keyword.loc, format!("'{}' declarations need an initializer.", keyword.token).as_str()));
+        } else if let Some(pattern) = patterns.iter().find(|p| !matches!(p, Pattern::Name(_, _))) {
+            return Err(Error::new(pattern.token().loc, "Destructuring declarations need an initializer."));
         }
 
         self.end_statement()?;
-        Ok(Stmt::Declaration(name, initializer))
+        Ok(Stmt::Declaration(keyword, patterns, values))
+    }
+
+    // only plain names directly after 'var' can be annotated, inside {} the ':' already renames a field
+    fn annotated_pattern(&mut self) -> Result<Pattern, Error> {
+        match self.pattern()? {
+            Pattern::Name(name, _) => Ok(Pattern::Name(name, self.annotation()?)),
+            pattern => Ok(pattern)
+        }
+    }
+
+    fn annotation(&mut self) -> Result<Option<LocToken>, Error> {
+        if cmp!(*self, Colon) {
+            Ok(Some(self.consume(Identifier("".to_string()))?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, Error> {
+        if cmp!(*self, LeftBracket) {
+            let bracket = self.previous();
+            self.groups.push(true);
+            let mut items = Vec::new();
+            while !self.check(RightBracket) {
+                items.push(self.pattern()?);
+                if !cmp!(*self, Comma) {
+                    break;
+                }
+            }
+            self.consume(RightBracket)?;
+            self.groups.pop();
+            Ok(Pattern::List(bracket, items))
+        } else if cmp!(*self, LeftBrace) {
+            let brace = self.previous();
+            self.groups.push(true);
+            let mut entries = Vec::new();
+            while !self.check(RightBrace) {
+                let key = self.consume(Identifier("".to_string()))?;
+                let pattern = if cmp!(*self, Colon) { self.pattern()? } else { Pattern::Name(key.clone(), None) };
+                entries.push((key, pattern));
+                if !cmp!(*self, Comma) {
+                    break;
+                }
+            }
+            self.consume(RightBrace)?;
+            self.groups.pop();
+            Ok(Pattern::Map(brace, entries))
+        } else {
+            Ok(Pattern::Name(self.consume(Identifier("".to_string()))?, None))
+        }
+    }
+
+    // the targets of a multiple assignment are parsed as expressions first, since we only know it is one once we see the ',' or '='
+    fn to_pattern(expr: Expr) -> Result<Pattern, Error> {
+        match expr {
+            Expr::Variable(name) => Ok(Pattern::Name(name, None)),
+            Expr::List(bracket, items) => Ok(Pattern::List(bracket, items.into_iter().map(Self::to_pattern).collect::<Result<_, _>>()?)),
+            other => Err(Error::new(Interpreter::get_loc_token_from_expr(&other).loc, "Invalid assignment target."))
+        }
+    }
+
+    fn check_duplicates(patterns: &[Pattern]) -> Result<(), Error> {
+        let names: Vec<&LocToken> = patterns.iter().flat_map(Pattern::names).collect();
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].iter().any(|other| other.token.to_string() == name.token.to_string()) {
+                return Err(Error::new(name.loc, format!("'{}' is bound more than once.", name.token).as_str()));
+            }
+        }
+        Ok(())
+    }
+
+    fn expression_list(&mut self) -> Result<Vec<Expr>, Error> {
+        let mut exprs = vec![self.awaitable()?];
+        while cmp!(*self, Comma) {
+            self.skip_newlines();
+            exprs.push(self.awaitable()?);
+        }
+        Ok(exprs)
+    }
+
+    fn function(&mut self, is_async: bool) -> Result<Stmt, Error> {
+        let name = self.consume(Identifier("".to_string()))?;
+        let params = self.parameters()?;
+        let returns = self.annotation()?;
+        cmp!(*self, Newline);
+        self.consume(LeftBrace)?;
+        Ok(Stmt::Function(name, params, returns, self.function_body(is_async)?, is_async))
+    }
+
+    fn class(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume(Identifier("".to_string()))?;
+        let superclass = if cmp!(*self, Less) {
+            let superclass = self.consume(Identifier("".to_string()))?;
+            if superclass.token.to_string() == name.token.to_string() {
+                return Err(Error::new(superclass.loc, "A class cannot inherit from itself."));
+            }
+            Some(Expr::Variable(superclass))
+        } else {
+            None
+        };
+        let mut traits = Vec::new();
+        if cmp!(*self, Implements) {
+            loop {
+                traits.push(Expr::Variable(self.consume(Identifier("".to_string()))?));
+                if !cmp!(*self, Comma) {
+                    break;
+                }
+            }
+        }
+        cmp!(*self, Newline);
+        self.consume(LeftBrace)?;
+        self.groups.push(false);
+        self.classes.push(superclass.is_some());
+        let mut methods = Vec::new();
+        while !self.check(RightBrace) && !self.is_at_end() {
+            if cmp!(*self, Semicolon, Newline) {
+                continue;
+            }
+            let is_async = cmp!(*self, Async);
+            self.consume(Fn)?;
+            methods.push(self.function(is_async)?);
+        }
+        self.classes.pop();
+        self.consume(RightBrace)?;
+        self.groups.pop();
+        Ok(Stmt::Class(name, superclass, traits, methods))
+    }
+
+    // methods written without a body are required, the others are defaults implementors inherit
+    fn trait_declaration(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume(Identifier("".to_string()))?;
+        cmp!(*self, Newline);
+        self.consume(LeftBrace)?;
+        self.groups.push(false);
+        self.classes.push(false);
+        let mut required = Vec::new();
+        let mut defaults = Vec::new();
+        while !self.check(RightBrace) && !self.is_at_end() {
+            if cmp!(*self, Semicolon, Newline) {
+                continue;
+            }
+            self.consume(Fn)?;
+            let method = self.consume(Identifier("".to_string()))?;
+            let params = self.parameters()?;
+            let returns = self.annotation()?;
+            if cmp!(*self, LeftBrace) {
+                defaults.push(Stmt::Function(method, params, returns, self.function_body(false)?, false));
+            } else {
+                required.push((method, params));
+                self.end_statement()?;
+            }
+        }
+        self.classes.pop();
+        self.consume(RightBrace)?;
+        self.groups.pop();
+        Ok(Stmt::Trait(name, required, defaults))
+    }
+
+    // enum Shape { Circle(r), Rect(w, h), Empty }
+    fn enumeration(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume(Identifier("".to_string()))?;
+        cmp!(*self, Newline);
+        self.consume(LeftBrace)?;
+        self.groups.push(true);
+        let mut variants: Vec<(LocToken, Vec<LocToken>)> = Vec::new();
+        while !self.check(RightBrace) {
+            let variant = self.consume(Identifier("".to_string()))?;
+            if variants.iter().any(|(v, _)| v.token.to_string() == variant.token.to_string()) {
+                return Err(Error::new(variant.loc, format!("Duplicate variant '{}' in enum '{}'.", variant.token, name.token).as_str()));
+            }
+            let mut fields = Vec::new();
+            if cmp!(*self, LeftParen) {
+                while !self.check(RightParen) {
+                    fields.push(self.consume(Identifier("".to_string()))?);
+                    if !cmp!(*self, Comma) {
+                        break;
+                    }
+                }
+                self.consume(RightParen)?;
+            }
+            variants.push((variant, fields));
+            if !cmp!(*self, Comma) {
+                break;
+            }
+        }
+        self.consume(RightBrace)?;
+        self.groups.pop();
+        if variants.is_empty() {
+            return Err(Error::new(name.loc, format!("Enum '{}' needs at least one variant.", name.token).as_str()));
+        }
+        Ok(Stmt::Enum(name, variants))
+    }
+
+    // match value { Circle(r): 3.14 * r * r, _: 0 }, with arms separated by commas or newlines
+    fn match_expression(&mut self) -> Result<Expr, Error> {
+        let keyword = self.previous();
+        let subject = self.expression()?;
+        cmp!(*self, Newline);
+        self.consume(LeftBrace)?;
+        self.groups.push(false);
+        let mut arms = Vec::new();
+        loop {
+            while cmp!(*self, Newline, Comma, Semicolon) {}
+            if self.check(RightBrace) {
+                break;
+            }
+            let case = self.case()?;
+            self.consume(Colon)?;
+            self.skip_newlines();
+            arms.push((case, self.expression()?));
+            if !(self.check(Newline) || self.check(Comma) || self.check(Semicolon) || self.check(RightBrace)) {
+                return Err(Error::new(self.peek().loc, "Expected ',' or newline after match arm."));
+            }
+        }
+        self.consume(RightBrace)?;
+        self.groups.pop();
+        if arms.is_empty() {
+            return Err(Error::new(keyword.loc, "A match needs at least one arm."));
+        }
+        Ok(Expr::Match(keyword, Box::new(subject), arms))
+    }
+
+    fn case(&mut self) -> Result<Case, Error> {
+        let token = self.advance();
+        match &token.token {
+            Identifier(name) if name == "_" => Ok(Case::Wildcard(token)),
+            Identifier(_) if cmp!(*self, LeftParen) => {
+                self.groups.push(true);
+                let mut fields = Vec::new();
+                while !self.check(RightParen) {
+                    fields.push(self.case()?);
+                    if !cmp!(*self, Comma) {
+                        break;
+                    }
+                }
+                self.consume(RightParen)?;
+                self.groups.pop();
+                Ok(Case::Variant(token, fields))
+            }
+            Identifier(_) => Ok(Case::Name(token)),
+            False | True | Null | Number(_) | String(_) => Ok(Case::Literal(token)),
+            other => Err(Error::new(token.loc, format!("Expected a pattern, but found '{}'.", other).as_str()))
+        }
+    }
+
+    // either a block or a single expression that is returned, as in fn (x) x * 2
+    fn lambda(&mut self) -> Result<Expr, Error> {
+        let keyword = self.previous();
+        let params = self.parameters()?;
+        let returns = self.annotation()?;
+        let body = if cmp!(*self, LeftBrace) {
+            self.function_body(false)?
+        } else {
+            let value = self.expression()?;
+            vec![Stmt::Return(keyword.clone(), Some(value))]
+        };
+        Ok(Expr::Lambda(keyword, params, returns, body))
+    }
+
+    fn parameters(&mut self) -> Result<Vec<Param>, Error> {
+        self.consume(LeftParen)?;
+        self.groups.push(true);
+        let mut params: Vec<Param> = Vec::new();
+        if !self.check(RightParen) {
+            loop {
+                if params.last().is_some_and(|p| p.variadic) {
+                    return Err(Error::new(self.peek().loc, "A variadic parameter must be the last parameter."));
+                }
+                let variadic = cmp!(*self, Ellipsis);
+                let name = self.consume(Identifier("".to_string()))?;
+                if params.iter().any(|p| p.name.token.to_string() == name.token.to_string()) {
+                    return Err(Error::new(name.loc, format!("Duplicate parameter '{}'.", name.token).as_str()));
+                }
+                let annotation = if variadic { None } else { self.annotation()? };
+                let default = if !variadic && cmp!(*self, Equal) {
+                    Some(self.expression()?)
+                } else {
+                    None
+                };
+                if default.is_none() && !variadic && params.iter().any(|p| p.default.is_some()) {
+                    return Err(Error::new(name.loc, format!("Parameter '{}' without a default cannot follow a parameter with a default.", name.token).as_str()));
+                }
+                params.push(Param { name, annotation, default, variadic });
+                if !cmp!(*self, Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(RightParen)?;
+        self.groups.pop();
+        Ok(params)
+    }
+
+    fn function_body(&mut self, is_async: bool) -> Result<Vec<Stmt>, Error> {
+        self.function_depth += 1;
+        let in_async = std::mem::replace(&mut self.in_async, is_async);
+        let body = self.block();
+        self.in_async = in_async;
+        self.function_depth -= 1;
+        body
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        if self.function_depth == 0 {
+            return Err(Error::new(keyword.loc, "Cannot return from top-level code."));
+        }
+        let value = if self.check(Semicolon) || self.check(Newline) || self.check(RightBrace) || self.is_at_end() {
+            None
+        } else {
+            let mut values = self.expression_list()?;
+            if values.len() == 1 {
+                values.pop()
+            } else {
+                Some(Expr::Tuple(keyword.clone(), values))
+            }
+        };
+        self.end_statement_if_not_else()?;
+        Ok(Stmt::Return(keyword, value))
+    }
+
+    fn yield_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        if self.function_depth == 0 {
+            return Err(Error::new(keyword.loc, "Cannot yield from top-level code."));
+        }
+        if self.in_async {
+            return Err(Error::new(keyword.loc, "Cannot yield inside an async function."));
+        }
+        let value = self.expression()?;
+        self.end_statement_if_not_else()?;
+        Ok(Stmt::Yield(keyword, Box::new(value)))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.end_statement_if_not_else()?;
+        Ok(Stmt::Throw(keyword, Box::new(value)))
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        cmp!(*self, Newline);
+        self.consume(LeftBrace)?;
+        let body = self.block()?;
+        self.skip_newlines();
+        let catch = if cmp!(*self, Catch) {
+            let name = match self.peek().token {
+                Identifier(_) => Some(self.advance()),
+                _ => None
+            };
+            cmp!(*self, Newline);
+            self.consume(LeftBrace)?;
+            let handler = self.block()?;
+            self.skip_newlines();
+            Some((name, handler))
+        } else {
+            None
+        };
+        let finally = if cmp!(*self, Finally) {
+            cmp!(*self, Newline);
+            self.consume(LeftBrace)?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(Error::new(keyword.loc, "Expected 'catch' or 'finally' after 'try' block."));
+        }
+        Ok(Stmt::Try(body, catch, finally))
     }
 
     fn log(&mut self) -> Result<Stmt, Error> {
-        let out = Ok(Stmt::Log(Box::new(self.expression()?)));
+        let out = Ok(Stmt::Log(Box::new(self.awaitable()?)));
         self.end_statement_if_not_else()?;
         out
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, Error> {
-        let out = Ok(Stmt::Expression(Box::new(self.expression()?)));
+        let expr = self.awaitable()?;
+        if self.check(Comma) || (matches!(expr, Expr::List(_, _)) && self.check(Equal)) {
+            let mut targets = vec![expr];
+            while cmp!(*self, Comma) {
+                self.skip_newlines();
+                targets.push(self.binary(level(LOWEST))?);
+            }
+            let equals = self.consume(Equal)?;
+            let patterns = targets.into_iter().map(Self::to_pattern).collect::<Result<Vec<_>, _>>()?;
+            Self::check_duplicates(&patterns)?;
+            let values = self.expression_list()?;
+            self.end_statement_if_not_else()?;
+            return Ok(Stmt::Assign(patterns, equals, values));
+        }
+        let out = Ok(Stmt::Expression(Box::new(expr)));
         self.end_statement_if_not_else();
         out
     }
@@ -201,13 +761,30 @@ impl<'a> Parser<'a> {
         self.assignment()
     }
 
+    // an async function keeps its place with generator frames, so it can only suspend where a statement's value is an await
+    fn awaitable(&mut self) -> Result<Expr, Error> {
+        if !cmp!(*self, Await) {
+            return self.expression();
+        }
+        let keyword = self.previous();
+        if self.function_depth > 0 && !self.in_async {
+            return Err(Error::new(keyword.loc, "Can only await inside an async function or at top level."));
+        }
+        Ok(Expr::Await(keyword, Box::new(self.expression()?)))
+    }
+
     fn assignment(&mut self) -> Result<Expr, Error> {
-        let expr = self.or()?;
+        let expr = self.ternary()?;
+        // [a, b] = ... is a destructuring statement, leave the '=' for expression_statement
+        if matches!(expr, Expr::List(_, _)) {
+            return Ok(expr);
+        }
         if cmp!(*self, Equal) {
             let equals = self.previous();
-            let value = self.assignment()?;
+            let value = if self.check(Await) { self.awaitable()? } else { self.assignment()? };
             match expr {
                 Expr::Variable(name) => Ok(Expr::Assign(name, Box::new(value))),
+                Expr::Get(object, name) => Ok(Expr::Set(object, name, Box::new(value))),
                 _ => Err(Error::new(equals.loc, "Invalid assignment target."))
             }
         } else {
@@ -215,127 +792,163 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn or(&mut self) -> Result<Expr, Error> {
-        let mut expr = self.and()?;
-
-        while cmp!(*self, Or, Xor) {
-            let op = self.previous();
-            let right = self.and()?;
-            expr = Expr::Logical(Box::new(expr), op, Box::new(right));
+    // binds looser than every binary operator and nests to the right, so a ? b : c ? d : e is a ? b : (c ? d : e)
+    fn ternary(&mut self) -> Result<Expr, Error> {
+        let condition = self.binary(level(LOWEST))?;
+        if !cmp!(*self, QuestionMark) {
+            return Ok(condition);
         }
+        let question = self.previous();
+        self.skip_newlines();
+        let then_value = self.ternary()?;
+        self.skip_newlines();
+        self.consume(Colon)?;
+        self.skip_newlines();
+        let else_value = self.ternary()?;
+        Ok(Expr::Ternary(Box::new(condition), question, Box::new(then_value), Box::new(else_value)))
+    }
 
-        Ok(expr)
+    fn operator(&self, token: &Token) -> Option<Operator> {
+        match token {
+            Identifier(name) => self.operators.get(name).copied(),
+            _ => OPERATORS.get(&token.to_string()).copied()
+        }
     }
 
-    fn and(&mut self) -> Result<Expr, Error> {
-        let mut expr = self.equality()?;
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr, Error> {
+        let mut expr = self.unary()?;
 
-        while cmp!(*self, And) {
-            let op = self.previous();
-            let right = self.equality()?;
-            expr = Expr::Logical(Box::new(expr), op, Box::new(right));
+        while let Some(operator) = self.operator(&self.peek().token) {
+            if operator.precedence < min_precedence {
+                break;
+            }
+            let op = self.advance();
+            self.skip_newlines();
+            let next = match operator.assoc {
+                Assoc::Left => operator.precedence + 1,
+                Assoc::Right => operator.precedence,
+            };
+            let right = self.binary(next)?;
+            expr = match op.token {
+                And | Or | Xor | QuestionQuestion => Expr::Logical(Box::new(expr), op, Box::new(right)),
+                PipeGreater => Self::pipe(expr, op, right),
+                Greater | GreaterEqual | Less | LessEqual => Self::comparison(expr, op, right),
+                _ => Expr::Binary(Box::new(expr), op, Box::new(right)),
+            };
         }
 
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expr, Error> {
-        let mut expr;
-        match self.comparison() {
-            Ok(left) => {
-                expr = left;
-                while cmp!(*self, BangEqual, EqualEqual) {
-                    let op = self.previous();
-                    expr = Expr::Binary(Box::new(expr), op, Box::new(self.comparison()?));
-                }
-                Ok(expr)
+    // 1 < x < 10 extends the comparison on its left into a chain, parentheses keep (1 < x) < 10 as it is
+    fn comparison(left: Expr, op: LocToken, right: Expr) -> Expr {
+        let ordering = |token: &Token| matches!(token, Greater | GreaterEqual | Less | LessEqual);
+        match left {
+            Expr::Binary(first, previous, middle) if ordering(&previous.token) => Expr::Comparison(first, vec![(previous, *middle), (op, right)]),
+            Expr::Comparison(first, mut rest) => {
+                rest.push((op, right));
+                Expr::Comparison(first, rest)
             }
-            Err(e) => Err(e)
+            left => Expr::Binary(Box::new(left), op, Box::new(right)),
         }
     }
 
-    fn comparison(&mut self) -> Result<Expr, Error> {
-        let mut expr;
-        match self.term() {
-            Ok(left) => {
-                expr = left;
-                while cmp!(*self, Greater, GreaterEqual, Less, LessEqual) {
-                    let op = self.previous();
-                    match self.term() {
-                        Ok(right) => expr = Expr::Binary(Box::new(expr), op, Box::new(right)),
-                        Err(e) => return Err(e)
-                    }
-                }
-                Ok(expr)
+    // xs |> f(a) is f(xs, a) and xs |> f is f(xs)
+    fn pipe(left: Expr, op: LocToken, right: Expr) -> Expr {
+        match right {
+            Expr::Call(callee, paren, mut args, named) => {
+                args.insert(0, left);
+                Expr::Call(callee, paren, args, named)
             }
-            Err(e) => Err(e)
+            callee => Expr::Call(Box::new(callee), op, vec![left], Vec::new()),
         }
     }
 
-    fn term(&mut self) -> Result<Expr, Error> {
-        let mut expr;
-        match self.factor() {
-            Ok(left) => {
-                expr = left;
-                while cmp!(*self, Minus, Plus) {
-                    let op = self.previous();
-                    match self.factor() {
-                        Ok(right) => expr = Expr::Binary(Box::new(expr), op, Box::new(right)),
-                        Err(e) => return Err(e)
-                    }
-                }
-                Ok(expr)
-            }
-            Err(e) => Err(e)
-        }
+    fn unary(&mut self) -> Result<Expr, Error> {
+        self.nest()?;
+        let expr = self.prefix();
+        self.depth -= 1;
+        expr
     }
 
-    fn factor(&mut self) -> Result<Expr, Error> {
-        let mut expr;
-        match self.range() {
-            Ok(left) => {
-                expr = left;
-                while cmp!(*self, Slash, Star) {
-                    let op = self.previous();
-                    match self.unary() {
-                        Ok(right) => expr = Expr::Binary(Box::new(expr), op, Box::new(right)),
-                        Err(e) => return Err(e)
-                    }
-                }
-                Ok(expr)
-            }
-            Err(e) => Err(e)
+    fn prefix(&mut self) -> Result<Expr, Error> {
+        if cmp!(*self, Not) {
+            let op = self.previous();
+            let right = self.binary(NOT)?;
+            Ok(Expr::Unary(op, Box::new(right)))
+        } else if cmp!(*self, Bang, Minus, Tilde) {
+            let op = self.previous();
+            let right = self.binary(level(UNARY))?;
+            Ok(Expr::Unary(op, Box::new(right)))
+        } else {
+            self.call()
         }
     }
 
-    fn range(&mut self) -> Result<Expr, Error> {
-        let mut expr;
-        match self.unary() {
-            Ok(left) => {
-                expr = left;
-                while cmp!(*self, Range) {
-                    let op = self.previous();
-                    match self.unary() {
-                        Ok(right) => expr = Expr::Binary(Box::new(expr), op, Box::new(right)),
-                        Err(e) => return Err(e)
+    fn infix(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume(Identifier("".to_string()))?;
+        let precedence = match self.advance() {
+            LocToken { token: Number(n), .. } if n.fract() == 0.0 && (LOWEST as f64..=POWER as f64).contains(&n) => n as u8,
+            token => return Err(Error::new(token.loc, format!("Expected an operator precedence between {} and {}.", LOWEST, POWER).as_str()))
+        };
+        let assoc = match self.peek().token {
+            Identifier(ref s) if s == "left" => Assoc::Left,
+            Identifier(ref s) if s == "right" => Assoc::Right,
+            _ => return Err(Error::new(self.peek().loc, "Expected 'left' or 'right' after operator precedence."))
+        };
+        self.advance();
+        self.end_statement()?;
+        self.operators.insert(name.token.to_string(), Operator { precedence: level(precedence), assoc });
+        Ok(Stmt::Infix(name, precedence, assoc))
+    }
+
+    fn call(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if cmp!(*self, LeftParen) {
+                let paren = self.previous();
+                self.groups.push(true);
+                let mut args = Vec::new();
+                let mut named = Vec::new();
+                if !self.check(RightParen) {
+                    loop {
+                        if matches!(self.peek().token, Identifier(_)) && self.tokens[self.position() + 1].token == Colon {
+                            let name = self.advance();
+                            self.advance();
+                            named.push((name, self.expression()?));
+                        } else if !named.is_empty() {
+                            return Err(Error::new(self.peek().loc, "Positional arguments cannot follow named arguments."));
+                        } else {
+                            args.push(self.expression()?);
+                        }
+                        if !cmp!(*self, Comma) {
+                            break;
+                        }
                     }
                 }
-                Ok(expr)
+                self.consume(RightParen)?;
+                self.groups.pop();
+                expr = Expr::Call(Box::new(expr), paren, args, named);
+            } else if cmp!(*self, Dot) {
+                let name = self.consume(Identifier("".to_string()))?;
+                expr = Expr::Get(Box::new(expr), name);
+            } else if cmp!(*self, QuestionDot) {
+                let name = self.consume(Identifier("".to_string()))?;
+                expr = Expr::SafeGet(Box::new(expr), name);
+            } else if cmp!(*self, LeftBracket) {
+                let bracket = self.previous();
+                self.groups.push(true);
+                let index = self.expression()?;
+                self.consume(RightBracket)?;
+                self.groups.pop();
+                expr = Expr::Index(Box::new(expr), bracket, Box::new(index));
+            } else {
+                break;
             }
-            Err(e) => Err(e)
         }
-    }
 
-    fn unary(&mut self) -> Result<Expr, Error> {
-        if cmp!(*self, Bang, Minus) {
-            let op = self.previous();
-            match self.unary() {
-                Ok(right) => Ok(Expr::Unary(op, Box::new(right))),
-                Err(e) => Err(e)
-            }
-        } else {
-            self.primary()
-        }
+        Ok(expr)
     }
 
     fn primary(&mut self) -> Result<Expr, Error> {
@@ -349,17 +962,120 @@ impl<'a> Parser<'a> {
                 self.advance();
                 Ok(Expr::Variable(token))
             }
-            LeftParen => {
+            Fn => {
+                self.advance();
+                self.lambda()
+            }
+            Match => {
                 self.advance();
-                match self.expression() {
-                    Ok(expr) => {
-                        match self.consume(RightParen) {
-                            Ok(_) => Ok(Expr::Grouping(Box::new(expr))),
-                            Err(e) => Err(e)
+                self.match_expression()
+            }
+            Await => Err(Error::new(token.loc, "'await' must start a statement, an initializer, an assignment or a return value.")),
+            This => {
+                self.advance();
+                if self.classes.is_empty() {
+                    return Err(Error::new(token.loc, "Cannot use 'this' outside of a class."));
+                }
+                Ok(Expr::This(token))
+            }
+            Super => {
+                self.advance();
+                match self.classes.last() {
+                    None => return Err(Error::new(token.loc, "Cannot use 'super' outside of a class.")),
+                    Some(false) => return Err(Error::new(token.loc, "Cannot use 'super' in a class with no superclass.")),
+                    Some(true) => {}
+                }
+                self.consume(Dot)?;
+                let method = self.consume(Identifier("".to_string()))?;
+                Ok(Expr::Super(token, method))
+            }
+            LeftBracket => {
+                self.advance();
+                self.groups.push(true);
+                let mut items = Vec::new();
+                while !self.check(RightBracket) {
+                    let item = self.expression()?;
+                    if items.is_empty() && self.check(For) {
+                        let comprehension = self.comprehension(token.clone(), Stmt::Yield(token, Box::new(item)))?;
+                        self.consume(RightBracket)?;
+                        self.groups.pop();
+                        return Ok(comprehension);
+                    }
+                    items.push(item);
+                    if !cmp!(*self, Comma) {
+                        break;
+                    }
+                }
+                self.consume(RightBracket)?;
+                self.groups.pop();
+                Ok(Expr::List(token, items))
+            }
+            LeftBrace => {
+                self.advance();
+                self.groups.push(true);
+                let mut entries = Vec::new();
+                while !self.check(RightBrace) {
+                    // a comprehension can compute its keys, so anything but a plain name or string before the ':' has to start one
+                    let start = self.cur;
+                    let key = match self.advance() {
+                        key @ LocToken { token: Identifier(_) | String(_), .. } if self.check(Colon) => key,
+                        other if entries.is_empty() => {
+                            self.cur = start;
+                            let key = self.expression()?;
+                            self.consume(Colon)?;
+                            let value = self.expression()?;
+                            let comprehension = self.map_comprehension(token, key, value)?;
+                            self.consume(RightBrace)?;
+                            self.groups.pop();
+                            return Ok(comprehension);
                         }
+                        other => return Err(Error::new(other.loc, format!("Expected a field name, but found '{}'.", other.token).as_str()))
+                    };
+                    self.consume(Colon)?;
+                    let value = self.expression()?;
+                    if entries.is_empty() && self.check(For) {
+                        let key = match key.token {
+                            Identifier(_) => Expr::Variable(key),
+                            _ => Expr::Literal(key),
+                        };
+                        let comprehension = self.map_comprehension(token, key, value)?;
+                        self.consume(RightBrace)?;
+                        self.groups.pop();
+                        return Ok(comprehension);
+                    }
+                    entries.push((key, value));
+                    if !cmp!(*self, Comma) {
+                        break;
+                    }
+                }
+                self.consume(RightBrace)?;
+                self.groups.pop();
+                Ok(Expr::Map(token, entries))
+            }
+            // a comma is what makes a tuple, (a) is just a grouping while (a,) and (a, b) are tuples
+            LeftParen => {
+                self.advance();
+                self.groups.push(true);
+                if cmp!(*self, RightParen) {
+                    self.groups.pop();
+                    return Ok(Expr::Tuple(token, Vec::new()));
+                }
+                let expr = self.expression()?;
+                if !self.check(Comma) {
+                    self.consume(RightParen)?;
+                    self.groups.pop();
+                    return Ok(Expr::Grouping(Box::new(expr)));
+                }
+                let mut items = vec![expr];
+                while cmp!(*self, Comma) {
+                    if self.check(RightParen) {
+                        break;
                     }
-                    Err(e) => Err(e)
+                    items.push(self.expression()?);
                 }
+                self.consume(RightParen)?;
+                self.groups.pop();
+                Ok(Expr::Tuple(token, items))
             }
             _ => {
                 Err(Error::new(token.loc, format!("Expected expression, but found '{}'.", token.token).as_str()))
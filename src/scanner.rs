@@ -4,7 +4,7 @@ use crate::token::*;
 use crate::icps;
 use crate::icps::Error;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Loc {
     pub line: usize,
     pub col: usize,
@@ -24,7 +24,7 @@ pub struct Scanner<'a> {
 }
 
 impl<'a> Scanner<'a> {
-    pub fn new(source: &str) -> Scanner {
+    pub fn new(source: &str) -> Scanner<'_> {
         Scanner {
             it: source.chars().peekable(),
             tokens: Vec::new(),
@@ -87,6 +87,8 @@ impl<'a> Scanner<'a> {
                 ')' => Ok(RightParen),
                 '{' => Ok(LeftBrace),
                 '}' => Ok(RightBrace),
+                '[' => Ok(LeftBracket),
+                ']' => Ok(RightBracket),
                 '@' => Ok(At),
                 ',' => Ok(Comma),
                 '+' => Ok(Plus),
@@ -113,14 +115,48 @@ impl<'a> Scanner<'a> {
                     }
                     _ => Ok(Slash)
                 },
-                '*' => Ok(Star),
+                '*' => match self.peek() {
+                    Some('*') => {
+                        self.next();
+                        Ok(StarStar)
+                    }
+                    _ => Ok(Star)
+                },
+                '%' => Ok(Percent),
+                // '//' already starts a line comment, so floor division is spelled '~/'
+                '~' => match self.peek() {
+                    Some('/') => {
+                        self.next();
+                        Ok(TildeSlash)
+                    }
+                    _ => Ok(Tilde)
+                },
+                '&' => Ok(Ampersand),
+                '|' => match self.peek() {
+                    Some('>') => {
+                        self.next();
+                        Ok(PipeGreater)
+                    }
+                    _ => Ok(Pipe)
+                },
+                '^' => Ok(Caret),
                 ';' => {
                     if self.peek() == Some(&'\n') {
                         icps::warn(self.cur.line, self.cur.col, "Redundant semicolon.");
                     }
                     Ok(Semicolon)
                 },
-                '?' => Ok(QuestionMark),
+                '?' => match self.peek() {
+                    Some('?') => {
+                        self.next();
+                        Ok(QuestionQuestion)
+                    }
+                    Some('.') => {
+                        self.next();
+                        Ok(QuestionDot)
+                    }
+                    _ => Ok(QuestionMark)
+                },
                 ':' => Ok(Colon),
                 '!' => match self.peek() {
                     Some('=') => {
@@ -141,6 +177,10 @@ impl<'a> Scanner<'a> {
                         self.next();
                         Ok(GreaterEqual)
                     }
+                    Some('>') => {
+                        self.next();
+                        Ok(GreaterGreater)
+                    }
                     _ => Ok(Greater)
                 },
                 '<' => match self.peek() {
@@ -148,6 +188,10 @@ impl<'a> Scanner<'a> {
                         self.next();
                         Ok(LessEqual)
                     }
+                    Some('<') => {
+                        self.next();
+                        Ok(LessLess)
+                    }
                     _ => Ok(Less)
                 },
                 '"' => {
@@ -158,9 +202,14 @@ impl<'a> Scanner<'a> {
                     match self.peek() {
                         Some(&'.') => {
                             self.next();
-                            Ok(Range)
+                            if self.peek() == Some(&'.') {
+                                self.next();
+                                Ok(Ellipsis)
+                            } else {
+                                Ok(Range)
+                            }
                         },
-                        Some(c) if c.is_digit(10) => {
+                        Some(c) if c.is_ascii_digit() => {
                             let decimal_part = self.collect_decimal_part();
 
                             if let Some(LocToken { token: Token::Number(number), .. }) = self.tokens.last_mut() {
@@ -172,15 +221,13 @@ impl<'a> Scanner<'a> {
                                 return Err(Error::new(self.cur, "Unexpected character '.'"));
                             }
                         },
-                        _ => {
-                            Err(Error::new(self.cur, "Unexpected character '.'"))
-                        }
+                        _ => Ok(Dot)
                     }
                 },
                 _ => {
-                    if c.is_digit(10) {
+                    if c.is_ascii_digit() {
                         self.number(c)
-                    } else if c.is_alphanumeric() {
+                    } else if c.is_alphanumeric() || c == '_' {
                         Ok(self.identifier(c))
                     } else {
                         Err(Error::new(self.cur, format!("Unexpected character {}", c).as_str()))
@@ -216,7 +263,7 @@ impl<'a> Scanner<'a> {
         number_string.push(start_char);
 
         while let Some(&next_char) = self.peek() {
-            if next_char.is_digit(10) {
+            if next_char.is_ascii_digit() {
                 number_string.push(self.next().unwrap());
             } else {
                 break;
@@ -231,7 +278,7 @@ impl<'a> Scanner<'a> {
         let mut decimal_part = String::new();
         decimal_part.push('.');
         while let Some(&next_char) = self.peek() {
-            if next_char.is_digit(10) {
+            if next_char.is_ascii_digit() {
                 decimal_part.push(self.next().unwrap());
             } else {
                 break;
@@ -244,7 +291,7 @@ impl<'a> Scanner<'a> {
         let mut s = String::new();
         s.push(c);
         while let Some(c) = self.peek() {
-            if c.is_alphanumeric() {
+            if c.is_alphanumeric() || *c == '_' {
                 s.push(self.next().unwrap());
             } else {
                 break;
@@ -0,0 +1,246 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use crate::generator::{Generator, Step};
+use crate::icps::Error;
+use crate::interpreter::Interpreter;
+use crate::scanner::LocToken;
+use crate::token::Value;
+
+pub struct Task {
+    pub id: usize,
+    body: Body,
+    state: State,
+    // tasks suspended until this one finishes
+    waiters: Vec<Rc<RefCell<Task>>>,
+    // what the await this task is suspended at evaluates to, or the error it throws
+    sent: Result<Value, Error>,
+    // set once something awaits the task, so its failure has been seen
+    observed: bool,
+}
+
+enum Body {
+    Frames(Generator),
+    // spawn(f), which calls f once the task first runs
+    Call(Value, LocToken),
+    // a task that finishes with the task it is waiting on, like a timer or a spawned call that returned a task
+    Forward,
+    Running,
+}
+
+enum State {
+    Pending,
+    Done(Value),
+    Failed(Error),
+}
+
+// virtual time only moves when every task is waiting on a timer, and then jumps straight to the next one
+pub enum Clock {
+    Real(Instant),
+    Virtual(f64),
+}
+
+// runs ready tasks one at a time in the order they became ready, so the same script always interleaves the same way
+pub struct Scheduler {
+    ready: VecDeque<Rc<RefCell<Task>>>,
+    // (due in ms, sequence number, task), kept sorted so timers due at the same time fire in the order they were set
+    timers: Vec<(f64, usize, Rc<RefCell<Task>>)>,
+    clock: Clock,
+    next_id: usize,
+    // how many tasks are running on the Rust stack right now
+    running: usize,
+    failed: Vec<Rc<RefCell<Task>>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler { ready: VecDeque::new(), timers: Vec::new(), clock: Clock::Real(Instant::now()), next_id: 0, running: 0, failed: Vec::new() }
+    }
+
+    pub fn use_virtual_clock(&mut self) {
+        self.clock = Clock::Virtual(0.0);
+    }
+
+    // milliseconds since the scheduler started
+    pub fn now(&self) -> f64 {
+        match &self.clock {
+            Clock::Real(start) => start.elapsed().as_secs_f64() * 1000.0,
+            Clock::Virtual(now) => *now,
+        }
+    }
+
+    pub fn in_task(&self) -> bool {
+        self.running > 0
+    }
+
+    fn task(&mut self, body: Body) -> Rc<RefCell<Task>> {
+        self.next_id += 1;
+        Rc::new(RefCell::new(Task { id: self.next_id, body, state: State::Pending, waiters: Vec::new(), sent: Ok(Value::Null), observed: false }))
+    }
+
+    pub fn start(&mut self, generator: Generator) -> Rc<RefCell<Task>> {
+        let task = self.task(Body::Frames(generator));
+        self.ready.push_back(task.clone());
+        task
+    }
+
+    pub fn spawn(&mut self, callee: Value, loc: &LocToken) -> Rc<RefCell<Task>> {
+        let task = self.task(Body::Call(callee, loc.clone()));
+        self.ready.push_back(task.clone());
+        task
+    }
+
+    pub fn sleep(&mut self, ms: f64) -> Rc<RefCell<Task>> {
+        let task = self.task(Body::Forward);
+        let due = self.now() + ms;
+        let seq = task.borrow().id;
+        let at = self.timers.partition_point(|(time, _, _)| *time <= due);
+        self.timers.insert(at, (due, seq, task.clone()));
+        task
+    }
+}
+
+impl Debug for Task {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<task {}>", self.id)
+    }
+}
+
+// tasks are only ever equal to themselves
+impl PartialEq for Task {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// an await at top level keeps the loop going until the task it waits on has finished
+pub fn run_until(interpreter: &mut Interpreter, task: &Rc<RefCell<Task>>, loc: &LocToken) -> Result<Value, Error> {
+    task.borrow_mut().observed = true;
+    loop {
+        match &task.borrow().state {
+            State::Done(value) => return Ok(value.clone()),
+            State::Failed(e) => return Err(e.clone()),
+            State::Pending => {}
+        }
+        if !tick(interpreter)? {
+            return Err(Error::new(loc.loc, "Runtime Error: Awaited task can never finish."));
+        }
+    }
+}
+
+// runs everything left once the script is done, a task that failed without anyone awaiting it fails the script
+pub fn drain(interpreter: &mut Interpreter) -> Result<(), Error> {
+    while tick(interpreter)? {}
+    let failed = std::mem::take(&mut interpreter.scheduler.failed);
+    for task in failed {
+        if let (false, State::Failed(e)) = (task.borrow().observed, &task.borrow().state) {
+            return Err(e.clone());
+        }
+    }
+    Ok(())
+}
+
+// runs one ready task, or fires the next timers, false when there is nothing left to do
+fn tick(interpreter: &mut Interpreter) -> Result<bool, Error> {
+    if let Some(task) = interpreter.scheduler.ready.pop_front() {
+        run(interpreter, task);
+        return Ok(true);
+    }
+    let scheduler = &mut interpreter.scheduler;
+    let due = match scheduler.timers.first() {
+        Some((due, _, _)) => *due,
+        None => return Ok(false),
+    };
+    match &mut scheduler.clock {
+        Clock::Real(start) => {
+            let wait = due - start.elapsed().as_secs_f64() * 1000.0;
+            if wait > 0.0 {
+                std::thread::sleep(Duration::from_secs_f64(wait / 1000.0));
+            }
+        }
+        Clock::Virtual(now) => *now = now.max(due),
+    }
+    let now = scheduler.now();
+    let fired = scheduler.timers.partition_point(|(time, _, _)| *time <= now);
+    for (_, _, task) in scheduler.timers.drain(..fired).collect::<Vec<_>>() {
+        finish(scheduler, &task, Ok(Value::Null));
+    }
+    Ok(true)
+}
+
+fn run(interpreter: &mut Interpreter, task: Rc<RefCell<Task>>) {
+    let (body, sent) = {
+        let mut t = task.borrow_mut();
+        (std::mem::replace(&mut t.body, Body::Running), std::mem::replace(&mut t.sent, Ok(Value::Null)))
+    };
+    match body {
+        Body::Frames(mut generator) => {
+            interpreter.scheduler.running += 1;
+            let step = generator.resume(interpreter, sent);
+            interpreter.scheduler.running -= 1;
+            match step {
+                Ok(Step::Await(value)) => {
+                    task.borrow_mut().body = Body::Frames(generator);
+                    wait(&mut interpreter.scheduler, &task, value);
+                }
+                Ok(Step::Done(value) | Step::Yield(value)) => finish(&mut interpreter.scheduler, &task, Ok(value)),
+                Err(e) => finish(&mut interpreter.scheduler, &task, Err(e)),
+            }
+        }
+        Body::Call(callee, loc) => {
+            interpreter.scheduler.running += 1;
+            let result = interpreter.call(callee, &loc, Vec::new());
+            interpreter.scheduler.running -= 1;
+            task.borrow_mut().body = Body::Forward;
+            match result {
+                Ok(value) => wait(&mut interpreter.scheduler, &task, value),
+                Err(e) => finish(&mut interpreter.scheduler, &task, Err(e)),
+            }
+        }
+        Body::Forward => finish(&mut interpreter.scheduler, &task, sent),
+        Body::Running => {}
+    }
+}
+
+// awaiting something that is not a task just gives the other ready tasks a turn
+fn wait(scheduler: &mut Scheduler, task: &Rc<RefCell<Task>>, value: Value) {
+    let awaited = match value {
+        Value::Task(awaited) => awaited,
+        value => {
+            task.borrow_mut().sent = Ok(value);
+            scheduler.ready.push_back(task.clone());
+            return;
+        }
+    };
+    awaited.borrow_mut().observed = true;
+    let sent = match &awaited.borrow().state {
+        State::Pending => None,
+        State::Done(value) => Some(Ok(value.clone())),
+        State::Failed(e) => Some(Err(e.clone())),
+    };
+    match sent {
+        None => awaited.borrow_mut().waiters.push(task.clone()),
+        Some(sent) => {
+            task.borrow_mut().sent = sent;
+            scheduler.ready.push_back(task.clone());
+        }
+    }
+}
+
+// a failure is thrown at the await of everything waiting on the task, where a try can catch it
+fn finish(scheduler: &mut Scheduler, task: &Rc<RefCell<Task>>, result: Result<Value, Error>) {
+    let waiters = std::mem::take(&mut task.borrow_mut().waiters);
+    if result.is_err() && waiters.is_empty() {
+        scheduler.failed.push(task.clone());
+    }
+    for waiter in waiters {
+        waiter.borrow_mut().sent = result.clone();
+        scheduler.ready.push_back(waiter);
+    }
+    task.borrow_mut().state = match result {
+        Ok(value) => State::Done(value),
+        Err(e) => State::Failed(e),
+    };
+}
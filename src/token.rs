@@ -1,5 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Display, format};
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::string::String;
 use lazy_static::lazy_static;
 
@@ -10,16 +12,30 @@ pub enum Token {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     At,
     Comma,
     Plus,
     Minus,
     Slash,
     Star,
+    StarStar,
+    Percent,
+    TildeSlash,
+    Ampersand,
+    Pipe,
+    PipeGreater,
+    Caret,
+    Tilde,
+    Dot,
     Range,
+    Ellipsis,
     Semicolon,
     Newline,
     QuestionMark,
+    QuestionQuestion,
+    QuestionDot,
     Colon,
 
     // Comparisons
@@ -31,6 +47,8 @@ pub enum Token {
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
 
     // Literals
     Identifier(String),
@@ -42,6 +60,7 @@ pub enum Token {
     Or,
     Xor,
     Not,
+    In,
     True,
     False,
     Class,
@@ -52,11 +71,26 @@ pub enum Token {
     Null,
     Log,
     Return,
+    Yield,
+    Async,
+    Await,
     Super,
     This,
     Fn,
+    Infix,
+    Enum,
+    Match,
+    Trait,
+    Implements,
+    Is,
+    Throw,
+    Try,
+    Catch,
+    Finally,
     Use,
     Var,
+    Let,
+    Const,
 
     // Special
     Eof,
@@ -70,6 +104,21 @@ pub enum Value {
     Range(f64, f64),
     String(String),
     Boolean(bool),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+    Tuple(Rc<Vec<Value>>),
+    Error(String, Loc),
+    Native(Native),
+    Function(Rc<Function>),
+    Class(Rc<class::Class>),
+    Instance(Rc<RefCell<class::Instance>>),
+    Trait(Rc<class::Trait>),
+    Generator(Rc<RefCell<generator::Generator>>),
+    Task(Rc<RefCell<scheduler::Task>>),
+    Enum(Rc<variant::Enum>),
+    // the enum and the tag of a variant that still needs its fields
+    Constructor(Rc<variant::Enum>, usize),
+    Variant(Rc<variant::Variant>),
     Null,
 }
 
@@ -80,16 +129,30 @@ impl Display for Token {
             RightParen => ")".to_string(),
             LeftBrace => "{".to_string(),
             RightBrace => "}".to_string(),
+            LeftBracket => "[".to_string(),
+            RightBracket => "]".to_string(),
             At => "@".to_string(),
             Comma => ",".to_string(),
             Plus => "+".to_string(),
             Minus => "-".to_string(),
             Slash => "/".to_string(),
             Star => "*".to_string(),
+            StarStar => "**".to_string(),
+            Percent => "%".to_string(),
+            TildeSlash => "~/".to_string(),
+            Ampersand => "&".to_string(),
+            Pipe => "|".to_string(),
+            PipeGreater => "|>".to_string(),
+            Caret => "^".to_string(),
+            Tilde => "~".to_string(),
+            Dot => ".".to_string(),
             Range => "..".to_string(),
+            Ellipsis => "...".to_string(),
             Semicolon => ";".to_string(),
             Newline => "\\n".to_string(),
             QuestionMark => "?".to_string(),
+            QuestionQuestion => "??".to_string(),
+            QuestionDot => "?.".to_string(),
             Colon => ":".to_string(),
             Bang => "!".to_string(),
             BangEqual => "!=".to_string(),
@@ -99,6 +162,8 @@ impl Display for Token {
             GreaterEqual => ">=".to_string(),
             Less => "<".to_string(),
             LessEqual => "<=".to_string(),
+            LessLess => "<<".to_string(),
+            GreaterGreater => ">>".to_string(),
             Identifier(s) => s.to_string(),
             String(s) => s.to_string(),
             Number(n) => n.to_string(),
@@ -106,6 +171,7 @@ impl Display for Token {
             Or => "or".to_string(),
             Xor => "xor".to_string(),
             Not => "not".to_string(),
+            In => "in".to_string(),
             True => "true".to_string(),
             False => "false".to_string(),
             Class => "class".to_string(),
@@ -116,11 +182,26 @@ impl Display for Token {
             Null => "null".to_string(),
             Log => "log".to_string(),
             Return => "return".to_string(),
+            Yield => "yield".to_string(),
+            Async => "async".to_string(),
+            Await => "await".to_string(),
             Super => "super".to_string(),
             This => "this".to_string(),
             Fn => "fn".to_string(),
+            Infix => "infix".to_string(),
+            Enum => "enum".to_string(),
+            Match => "match".to_string(),
+            Trait => "trait".to_string(),
+            Implements => "implements".to_string(),
+            Is => "is".to_string(),
+            Throw => "throw".to_string(),
+            Try => "try".to_string(),
+            Catch => "catch".to_string(),
+            Finally => "finally".to_string(),
             Use => "use".to_string(),
             Var => "var".to_string(),
+            Let => "let".to_string(),
+            Const => "const".to_string(),
             Eof => "EOF".to_string(),
             UnterminatedString => "unterminated string".to_string(),
             Unknown(c) => "unknown".to_string()
@@ -139,16 +220,30 @@ impl PartialEq for Token {
         (RightParen, RightParen) |
         (LeftBrace, LeftBrace) |
         (RightBrace, RightBrace) |
+        (LeftBracket, LeftBracket) |
+        (RightBracket, RightBracket) |
         (At, At) |
         (Comma, Comma) |
         (Plus, Plus) |
         (Minus, Minus) |
         (Slash, Slash) |
         (Star, Star) |
+        (StarStar, StarStar) |
+        (Percent, Percent) |
+        (TildeSlash, TildeSlash) |
+        (Ampersand, Ampersand) |
+        (Pipe, Pipe) |
+        (PipeGreater, PipeGreater) |
+        (Caret, Caret) |
+        (Tilde, Tilde) |
+        (Dot, Dot) |
         (Range, Range) |
+        (Ellipsis, Ellipsis) |
         (Semicolon, Semicolon) |
         (Newline, Newline) |
         (QuestionMark, QuestionMark) |
+        (QuestionQuestion, QuestionQuestion) |
+        (QuestionDot, QuestionDot) |
         (Colon, Colon) |
         (Bang, Bang) |
         (BangEqual, BangEqual) |
@@ -158,10 +253,13 @@ impl PartialEq for Token {
         (GreaterEqual, GreaterEqual) |
         (Less, Less) |
         (LessEqual, LessEqual) |
+        (LessLess, LessLess) |
+        (GreaterGreater, GreaterGreater) |
         (And, And) |
         (Or, Or) |
         (Xor, Xor) |
         (Not, Not) |
+        (In, In) |
         (True, True) |
         (False, False) |
         (Class, Class) |
@@ -172,11 +270,26 @@ impl PartialEq for Token {
         (Null, Null) |
         (Log, Log) |
         (Return, Return) |
+        (Yield, Yield) |
+        (Async, Async) |
+        (Await, Await) |
         (Super, Super) |
         (This, This) |
         (Fn, Fn) |
+        (Infix, Infix) |
+        (Enum, Enum) |
+        (Match, Match) |
+        (Trait, Trait) |
+        (Implements, Implements) |
+        (Is, Is) |
+        (Throw, Throw) |
+        (Try, Try) |
+        (Catch, Catch) |
+        (Finally, Finally) |
         (Use, Use) |
         (Var, Var) |
+        (Let, Let) |
+        (Const, Const) |
         (Eof, Eof) |
         (UnterminatedString, UnterminatedString)
     )
@@ -196,6 +309,21 @@ impl Display for Value {
             Value::Range(start, end) => write!(f, "{}..{}", start, end),
             Value::String(s) => write!(f, "{}", s),
             Value::Boolean(b) => write!(f, "{}", b),
+            Value::List(items) => write!(f, "[{}]", items.borrow().iter().map(Value::repr).collect::<Vec<_>>().join(", ")),
+            Value::Tuple(items) if items.len() == 1 => write!(f, "({},)", items[0].repr()),
+            Value::Tuple(items) => write!(f, "({})", items.iter().map(Value::repr).collect::<Vec<_>>().join(", ")),
+            Value::Error(message, loc) => write!(f, "[{}:{}] {}", loc.line, loc.col, message),
+            Value::Map(entries) => write!(f, "{{{}}}", entries.borrow().iter().map(|(k, v)| format!("{}: {}", k, v.repr())).collect::<Vec<_>>().join(", ")),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
+            Value::Function(function) => write!(f, "{:?}", function),
+            Value::Class(class) => write!(f, "{:?}", class),
+            Value::Instance(instance) => write!(f, "{:?}", instance.borrow()),
+            Value::Trait(t) => write!(f, "{:?}", t),
+            Value::Generator(generator) => write!(f, "{:?}", generator.borrow()),
+            Value::Task(task) => write!(f, "{:?}", task.borrow()),
+            Value::Enum(ty) => write!(f, "{:?}", ty),
+            Value::Constructor(ty, tag) => write!(f, "<constructor {}.{}>", ty.name.token, ty.variants[*tag].0),
+            Value::Variant(variant) => write!(f, "{:?}", variant),
             Value::Null => write!(f, "null")
         }
     }
@@ -239,6 +367,53 @@ impl Value {
         }
     }
 
+    // how the value is written inside a collection, where strings keep their quotes
+    pub fn repr(&self) -> String {
+        match self {
+            Value::String(s) => Self::quote(s),
+            v => v.to_string()
+        }
+    }
+
+    // escapes what would otherwise end the quotes early or break the line
+    pub fn quote(s: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in s.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "Number",
+            Value::Range(_, _) => "Range",
+            Value::String(_) => "String",
+            Value::Boolean(_) => "Boolean",
+            Value::List(_) => "List",
+            Value::Map(_) => "Map",
+            Value::Tuple(_) => "Tuple",
+            Value::Error(_, _) => "Error",
+            Value::Native(_) | Value::Function(_) | Value::Constructor(_, _) => "Function",
+            Value::Class(_) => "Class",
+            Value::Instance(_) => "Instance",
+            Value::Trait(_) => "Trait",
+            Value::Generator(_) => "Generator",
+            Value::Task(_) => "Task",
+            Value::Enum(_) => "Enum",
+            Value::Variant(_) => "Variant",
+            Value::Null => "Null",
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match *self {
             Value::Boolean(b) => b,
@@ -264,18 +439,41 @@ lazy_static! {
         m.insert("else", Else);
         m.insert("while", While);
         m.insert("for", For);
+        m.insert("in", In);
         m.insert("null", Null);
         m.insert("log", Log);
         m.insert("return", Return);
+        m.insert("yield", Yield);
+        m.insert("async", Async);
+        m.insert("await", Await);
         m.insert("super", Super);
         m.insert("this", This);
         m.insert("fn", Fn);
+        m.insert("infix", Infix);
+        m.insert("enum", Enum);
+        m.insert("match", Match);
+        m.insert("trait", Trait);
+        m.insert("implements", Implements);
+        m.insert("is", Is);
+        m.insert("throw", Throw);
+        m.insert("try", Try);
+        m.insert("catch", Catch);
+        m.insert("finally", Finally);
         m.insert("use", Use);
         m.insert("var", Var);
+        m.insert("let", Let);
+        m.insert("const", Const);
         m
     };
 }
 
 pub use Token::*;
 use crate::ast::Expr;
-use crate::icps;
\ No newline at end of file
+use crate::builtins::Native;
+use crate::class;
+use crate::variant;
+use crate::generator;
+use crate::scheduler;
+use crate::function::Function;
+use crate::icps;
+use crate::scanner::Loc;
\ No newline at end of file
@@ -0,0 +1,56 @@
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use crate::scanner::LocToken;
+use crate::token::Value;
+
+pub struct Enum {
+    pub name: LocToken,
+    // each variant's name with the names of its fields
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+impl Enum {
+    pub fn tag(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|(variant, _)| variant == name)
+    }
+}
+
+impl Debug for Enum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<enum {}>", self.name.token)
+    }
+}
+
+impl PartialEq for Enum {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// a value built by one of an enum's constructors, like Circle(2)
+#[derive(PartialEq)]
+pub struct Variant {
+    pub ty: Rc<Enum>,
+    pub tag: usize,
+    pub values: Vec<Value>,
+}
+
+impl Variant {
+    pub fn name(&self) -> &str {
+        &self.ty.variants[self.tag].0
+    }
+
+    pub fn field(&self, name: &str) -> Option<Value> {
+        self.ty.variants[self.tag].1.iter().position(|field| field == name).map(|i| self.values[i].clone())
+    }
+}
+
+impl Debug for Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.values.is_empty() {
+            write!(f, "{}", self.name())
+        } else {
+            write!(f, "{}({})", self.name(), self.values.iter().map(Value::repr).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{fail, run};
+
+#[test]
+fn loop_variable_takes_the_type_of_the_items() {
+    let source = "
+for s in [\"a\", \"b\"] { log s < \"b\" }
+for f in [fn () 1] { log f() }
+log [s < \"b\" for s in [\"a\", \"b\"]]
+log [f() for f in [fn () 2]]
+for n in 0..2 { log n + 1 }
+";
+    assert_eq!(run("loop_types", source), "true\nfalse\n1\n[true, false]\n[2]\n1\n2\n3\n");
+}
+
+#[test]
+fn unannotated_mismatches_still_run() {
+    let source = "
+log \"start\"
+if false { log 1 - \"a\" }
+if false { log -\"a\" }
+if false { log 1 < \"a\" }
+fn f(n) { if false { return n - \"a\" } return n }
+log f(2)
+";
+    assert_eq!(run("unannotated", source), "start\n2\n");
+}
+
+#[test]
+fn annotated_mismatches_are_rejected() {
+    let cases = [
+        ("var x: Number = 1\nif false { log x - \"a\" }\n", "[2:13] Type Error: Cannot apply '-' to 'Number' and 'String'."),
+        ("fn f(): String { return \"a\" }\nif false { log 1 - f() }\n", "[2:13] Type Error: Cannot apply '-' to 'Number' and 'String'."),
+        ("fn g(n: Number) { if false { log -(n + 1) - \"b\" } }\n", "[1:25] Type Error: Cannot apply '-' to 'Number' and 'String'."),
+        ("var s: String = \"a\"\nlog -s\n", "[2:4] Type Error: Cannot apply '-' to 'String'."),
+        ("var x: Number = \"a\"\n", "[1:4] Type Error: Cannot initialize 'x' declared as 'Number' with a 'String'."),
+    ];
+    for (i, (source, error)) in cases.iter().enumerate() {
+        assert_eq!(fail(&format!("annotated_{}", i), source).trim_end(), *error);
+    }
+}
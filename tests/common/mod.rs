@@ -0,0 +1,35 @@
+use std::process::{Command, Output};
+
+fn output(name: &str, source: &str, args: &[&str]) -> Output {
+    let path = std::env::temp_dir().join(format!("icps_{}_{}.icps", name, std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_icps")).arg(&path).args(args).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+// runs a script through the icps binary and returns what it logged
+#[allow(dead_code)]
+pub fn run(name: &str, source: &str) -> String {
+    run_with(name, source, &[])
+}
+
+#[allow(dead_code)]
+pub fn run_with(name: &str, source: &str, args: &[&str]) -> String {
+    let output = output(name, source, args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// runs a script that has to fail and returns the error it reported, fail_with also gives what it logged
+#[allow(dead_code)]
+pub fn fail(name: &str, source: &str) -> String {
+    fail_with(name, source, &[]).1
+}
+
+#[allow(dead_code)]
+pub fn fail_with(name: &str, source: &str, args: &[&str]) -> (String, String) {
+    let output = output(name, source, args);
+    assert!(!output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{fail, run};
+
+#[test]
+fn the_middle_operand_is_evaluated_once() {
+    let source = "
+var calls = 0
+fn mid() {
+    calls = calls + 1
+    return 5
+}
+log 1 < mid() < 10
+log calls
+log 1 < mid() < 3
+log calls
+log 10 < mid() < 20
+log calls
+";
+    assert_eq!(run("middle", source), "true\n1\nfalse\n2\nfalse\n3\n");
+}
+
+#[test]
+fn a_false_link_skips_the_rest_of_the_chain() {
+    let source = "
+fn boom() { throw \"evaluated\" }
+log 3 < 1 < boom()
+log 1 <= 1 < 2 >= 2
+";
+    assert_eq!(run("short_circuit", source), "false\ntrue\n");
+}
+
+#[test]
+fn strings_and_sequences_order_lexicographically() {
+    let source = "
+log \"apple\" < \"banana\" < \"cherry\"
+log \"b\" > \"a\"
+log (1, 2) < (1, 3)
+log [1, 2] < [2]
+";
+    assert_eq!(run("lexicographic", source), "true\ntrue\ntrue\ntrue\n");
+}
+
+#[test]
+fn mixed_types_cannot_be_compared() {
+    let stderr = fail("mixed", "log 1 < 2 < \"a\"\n");
+    assert!(stderr.contains("[1:7] Runtime Error: Cannot compare 'Number' with 'String'."));
+}
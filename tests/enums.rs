@@ -0,0 +1,64 @@
+mod common;
+
+use common::{fail, fail_with, run};
+
+const SHAPE: &str = "
+enum Shape { Circle(r), Rect(w, h), Empty }
+";
+
+#[test]
+fn match_arms_destructure_variants() {
+    let source = format!("{}{}", SHAPE, "
+fn area(s) {
+    return match s {
+        Circle(r): 3 * r * r,
+        Rect(w, h): w * h,
+        Empty: 0
+    }
+}
+log area(Circle(2))
+log area(Rect(2, 5))
+log area(Empty)
+");
+    assert_eq!(run("area", &source), "12\n10\n0\n");
+}
+
+#[test]
+fn variants_print_and_compare_by_tag_and_fields() {
+    let source = format!("{}{}", SHAPE, "
+log Rect(1, 2)
+log Rect(1, 2) == Rect(1, 2)
+log Rect(1, 2) == Rect(2, 1)
+log Circle(1) == Rect(1, 1)
+");
+    assert_eq!(run("equality", &source), "Rect(1, 2)\ntrue\nfalse\nfalse\n");
+}
+
+#[test]
+fn a_match_missing_a_variant_warns_and_fails_when_nothing_matches() {
+    let source = format!("{}{}", SHAPE, "
+fn radius(s) {
+    return match s { Circle(r): r }
+}
+log radius(Circle(7))
+log radius(Rect(1, 1))
+");
+    let (stdout, stderr) = fail_with("partial", &source, &[]);
+    assert_eq!(stdout, "7\n");
+    assert!(stderr.contains("Warning: Match over 'Shape' does not cover 'Rect', 'Empty'."));
+    assert!(stderr.contains("Runtime Error: No arm matched 'Rect(1, 1)'."));
+}
+
+#[test]
+fn a_wildcard_arm_covers_the_other_variants() {
+    let source = format!("{}{}", SHAPE, "
+log match Empty { Circle(r): r, _: 0 }
+");
+    assert_eq!(run("wildcard", &source), "0\n");
+}
+
+#[test]
+fn no_matching_arm_is_an_error() {
+    let stderr = fail("none", "log match 3 { 1: \"one\", 2: \"two\" }\n");
+    assert!(stderr.contains("Runtime Error: No arm matched '3'."));
+}
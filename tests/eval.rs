@@ -0,0 +1,37 @@
+mod common;
+
+use common::{fail, run};
+
+#[test]
+fn eval_runs_in_the_callers_scope() {
+    let source = "
+var x = 10
+log eval(\"x + 1\")
+eval(\"var y = x * 2\")
+log y
+fn f() {
+    var local = 3
+    return eval(\"local + x\")
+}
+log f()
+";
+    assert_eq!(run("caller", source), "11\n20\n13\n");
+}
+
+#[test]
+fn isolated_eval_sees_only_builtins_and_keeps_its_bindings() {
+    let source = "
+log eval(\"len([1, 2])\", true)
+eval(\"var z = 1\", true)
+log defined(\"z\")
+";
+    assert_eq!(run("isolated", source), "2\nfalse\n");
+    let stderr = fail("isolated_scope", "var x = 1\nlog eval(\"x + 1\", true)\n");
+    assert!(stderr.contains("[1:1] Undefined variable 'x'.\n    in eval called from [2:7]"));
+}
+
+#[test]
+fn errors_point_into_the_evaluated_string_and_at_the_call() {
+    let stderr = fail("location", "fn g() {\n    return eval(\"1 +\n nope\")\n}\nlog g()\n");
+    assert!(stderr.contains("[2:5] Undefined variable 'nope'.\n    in eval called from [2:14]"));
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::{fail_with, run};
+
+#[test]
+fn generators_run_only_as_far_as_they_are_asked() {
+    let source = "
+fn naturals() {
+    var n = 0
+    while true {
+        log \"making \" + n
+        yield n
+        n = n + 1
+    }
+}
+var g = naturals()
+log \"created\"
+log next(g)
+log next(g)
+";
+    assert_eq!(run("lazy", source), "created\nmaking 0\n0\nmaking 1\n1\n");
+}
+
+#[test]
+fn return_ends_iteration() {
+    let source = "
+fn upto(limit) {
+    var i = 0
+    while true {
+        if i == limit { return }
+        yield i
+        i = i + 1
+    }
+}
+for x in upto(3) { log x }
+var u = upto(1)
+log next(u)
+log next(u)
+log next(u)
+";
+    assert_eq!(run("return", source), "0\n1\n2\n0\nnull\nnull\n");
+}
+
+#[test]
+fn finally_runs_once_a_generator_inside_try_is_exhausted() {
+    let source = "
+fn stream(n) {
+    try {
+        for i in 1..n {
+            yield i
+        }
+    } finally {
+        log \"closed\"
+    }
+}
+for x in stream(3) { log x }
+";
+    assert_eq!(run("stream", source), "1\n2\n3\nclosed\n");
+}
+
+#[test]
+fn catch_and_finally_can_yield() {
+    let source = "
+fn safe() {
+    try {
+        yield 1
+        throw \"boom\"
+    } catch e {
+        log \"caught \" + e
+        yield 2
+    } finally {
+        yield 3
+    }
+    yield 4
+}
+for x in safe() { log x }
+";
+    assert_eq!(run("catch", source), "1\ncaught boom\n2\n3\n4\n");
+}
+
+#[test]
+fn return_inside_try_runs_finally_before_ending() {
+    let source = "
+fn early() {
+    try {
+        yield 1
+        return 0
+    } finally {
+        log \"cleanup\"
+    }
+    yield 2
+}
+for x in early() { log x }
+";
+    assert_eq!(run("finally_return", source), "1\ncleanup\n");
+}
+
+#[test]
+fn errors_pass_through_finally() {
+    let source = "
+fn fails() {
+    try { yield 1 } finally { log \"fin\" }
+    var y = 1 / nothing
+}
+for x in fails() { log x }
+";
+    let (stdout, stderr) = fail_with("through", source, &[]);
+    assert_eq!(stdout, "1\nfin\n");
+    assert!(stderr.contains("Undefined variable 'nothing'."));
+}
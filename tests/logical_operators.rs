@@ -0,0 +1,75 @@
+mod common;
+
+use common::run;
+
+// every operand with whether it is truthy, null and the empty string are the only falsy values besides false
+const OPERANDS: &[(&str, bool)] = &[
+    ("true", true),
+    ("false", false),
+    ("null", false),
+    ("\"\"", false),
+    ("\"text\"", true),
+    ("0", true),
+    ("1", true),
+    ("[]", true),
+];
+
+#[test]
+fn truth_tables() {
+    let mut source = String::new();
+    let mut expected = String::new();
+    for (a, a_truthy) in OPERANDS {
+        source.push_str(&format!("log not {}\n", a));
+        expected.push_str(&format!("{}\n", !a_truthy));
+        for (b, b_truthy) in OPERANDS {
+            for (op, result) in [("and", *a_truthy && *b_truthy), ("or", *a_truthy || *b_truthy), ("xor", a_truthy != b_truthy)] {
+                source.push_str(&format!("log {} {} {}\n", a, op, b));
+                expected.push_str(&format!("{}\n", result));
+            }
+        }
+    }
+    assert_eq!(run("truth_tables", &source), expected);
+}
+
+#[test]
+fn precedence() {
+    let source = "
+log true or true xor true
+log false and true xor true
+log true xor true and false
+log not false and false
+log not true or true
+log not 1 == 2
+log not not 0
+log true xor false xor true
+";
+    assert_eq!(run("precedence", source), "true\ntrue\ntrue\nfalse\ntrue\ntrue\ntrue\nfalse\n");
+}
+
+#[test]
+fn short_circuit() {
+    let source = "
+fn side(value) {
+    log \"ran\"
+    return value
+}
+log false and side(true)
+log true or side(false)
+log true xor side(true)
+log false xor side(true)
+";
+    assert_eq!(run("short_circuit", source), "false\ntrue\nran\nfalse\nran\ntrue\n");
+}
+
+#[test]
+fn infix_levels_are_unchanged() {
+    let source = "
+fn dot(a, b) { return a * 10 + b }
+infix dot 11 left
+log 1 + 2 dot 3
+fn both(a, b) { return a and b }
+infix both 2 left
+log false or true both false
+";
+    assert_eq!(run("infix_levels", source), "24\nfalse\n");
+}
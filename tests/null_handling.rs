@@ -0,0 +1,36 @@
+mod common;
+
+use common::{fail, run};
+
+#[test]
+fn safe_access_skips_the_rest_of_the_chain() {
+    let source = "
+var m = null
+log m?.x.y
+log m?.x.y()
+log m?.x[0].z
+fn f() { return m?.g() }
+log f()
+var n = {\"x\": {\"y\": 5}}
+log n?.x.y
+";
+    assert_eq!(run("chain", source), "null\nnull\nnull\nnull\n5\n");
+}
+
+#[test]
+fn grouping_ends_the_chain() {
+    assert_eq!(fail("grouping", "var m = null\nlog (m?.x).y\n"), "[2:5] Runtime Error: Only maps and instances have fields.\n");
+}
+
+#[test]
+fn null_coalescing_and_uninitialized_variables() {
+    let source = "
+var a
+log null ?? \"default\"
+log 0 ?? \"default\"
+a = 1
+log a
+";
+    assert_eq!(run("coalesce", source), "default\n0\n1\n");
+    assert_eq!(fail("uninitialized", "var a\nlog a\n"), "[2:4] Runtime Error: Cannot use variable 'a' before assignment.\n");
+}
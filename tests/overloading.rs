@@ -0,0 +1,79 @@
+mod common;
+
+use common::{fail, run};
+
+const VEC: &str = "
+class Vec {
+    fn init(x, y) {
+        this.x = x
+        this.y = y
+    }
+    fn add(o) { return Vec(this.x + o.x, this.y + o.y) }
+    fn sub(o) { return Vec(this.x - o.x, this.y - o.y) }
+    fn mul(k) { return Vec(this.x * k, this.y * k) }
+    fn rmul(k) { return Vec(k * this.x, k * this.y) }
+    fn div(k) { return Vec(this.x / k, this.y / k) }
+    fn neg() { return Vec(-this.x, -this.y) }
+    fn eq(o) { return this.x == o.x and this.y == o.y }
+    fn lt(o) { return this.x < o.x }
+    fn str() { return \"Vec(\" + this.x + \", \" + this.y + \")\" }
+}
+var a = Vec(1, 2)
+var b = Vec(3, 4)
+";
+
+#[test]
+fn arithmetic_dispatches_to_methods() {
+    let source = format!("{}{}", VEC, "
+log a + b
+log b - a
+log a * 2
+log b / 2
+log -a
+");
+    assert_eq!(run("arithmetic", &source), "Vec(4, 6)\nVec(2, 2)\nVec(2, 4)\nVec(1.5, 2)\nVec(-1, -2)\n");
+}
+
+#[test]
+fn a_number_on_the_left_reflects_through_the_r_methods() {
+    let source = format!("{}{}", VEC, "
+log 2 * a
+class Money {
+    fn init(c) { this.c = c }
+    fn radd(n) { return Money(this.c + n) }
+    fn str() { return this.c + \"c\" }
+}
+log 5 + Money(10)
+");
+    assert_eq!(run("reflected", &source), "Vec(2, 4)\n15c\n");
+}
+
+#[test]
+fn comparisons_fall_back_to_the_mirrored_method() {
+    let source = format!("{}{}", VEC, "
+log a == Vec(1, 2)
+log a != b
+log a < b
+log b > a
+log a <= b
+log a >= b
+");
+    assert_eq!(run("comparison", &source), "true\ntrue\ntrue\ntrue\ntrue\nfalse\n");
+}
+
+#[test]
+fn str_prints_instances_inside_containers() {
+    let source = format!("{}{}", VEC, "
+log [a, b]
+log {\"a\": a}
+");
+    assert_eq!(run("str", &source), "[Vec(1, 2), Vec(3, 4)]\n{a: Vec(1, 2)}\n");
+}
+
+#[test]
+fn operands_no_method_handles_are_an_error() {
+    let stderr = fail("add", "class P { fn add(o) { return 1 } }\nlog 2 * P()\n");
+    assert!(stderr.contains("Runtime Error: Cannot apply '*' to 'Number' and 'P'."));
+    let stderr = fail("radd", "class P { fn add(o) { return 1 } }\nlog 1 + P()\n");
+    assert!(stderr.contains("Runtime Error: Cannot apply '+' to 'Number' and 'P'."));
+}
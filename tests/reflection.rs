@@ -0,0 +1,49 @@
+mod common;
+
+use common::{fail, run};
+
+#[test]
+fn locals_holds_the_bindings_of_the_current_function() {
+    let source = "
+var top = 1
+fn f(a) {
+    var b = 2
+    log locals()
+    if true {
+        var c = 3
+        log locals()
+    }
+}
+f(5)
+";
+    assert_eq!(run("locals", source), "{a: 5, b: 2}\n{a: 5, b: 2, c: 3}\n");
+}
+
+#[test]
+fn globals_holds_top_level_bindings_but_not_builtins() {
+    let source = "
+var top = 1
+fn f() { return globals()[\"top\"] }
+log globals()
+log f()
+log locals() == globals()
+log globals()[\"f\"] == f
+";
+    assert_eq!(run("globals", source), "{f: <fn f>, top: 1}\n1\ntrue\ntrue\n");
+    let stderr = fail("builtins", "log globals()[\"len\"]\n");
+    assert!(stderr.contains("Runtime Error: Undefined field 'len'."));
+}
+
+#[test]
+fn defined_checks_a_binding_without_raising() {
+    let source = "
+fn f(a) {
+    var b = 2
+    log defined(\"b\")
+    log defined(\"zzz\")
+}
+f(5)
+log defined(\"len\")
+";
+    assert_eq!(run("defined", source), "true\nfalse\ntrue\n");
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{fail_with, run_with};
+
+#[test]
+fn tasks_interleave_in_timer_order() {
+    let source = "
+async fn worker(name, delay) {
+    log name + \" start \" + now()
+    await sleep(delay)
+    log name + \" woke \" + now()
+    return name
+}
+var a = spawn(fn () worker(\"a\", 30))
+var b = spawn(fn () worker(\"b\", 10))
+var c = worker(\"c\", 20)
+log \"spawned\"
+log await c
+log await a
+log \"after \" + now()
+";
+    let expected = "spawned\nc start 0\na start 0\nb start 0\nb woke 10\nc woke 20\nc\na woke 30\na\nafter 30\n";
+    assert_eq!(run_with("interleave", source, &["--virtual-clock"]), expected);
+}
+
+#[test]
+fn timers_due_together_fire_in_the_order_they_were_set() {
+    let source = "
+async fn tick(name) {
+    await sleep(5)
+    log name
+}
+var tasks = [tick(\"x\"), tick(\"y\"), tick(\"z\")]
+for t in tasks { await t }
+log now()
+";
+    assert_eq!(run_with("same_time", source, &["--virtual-clock"]), "x\ny\nz\n5\n");
+}
+
+#[test]
+fn awaiting_a_task_that_waits_on_itself_fails() {
+    let source = "
+async fn forever() {
+    await forever_task
+}
+var forever_task = null
+forever_task = forever()
+log \"before\"
+await forever_task
+";
+    let (out, err) = fail_with("never", source, &["--virtual-clock"]);
+    assert_eq!(out, "before\n");
+    assert_eq!(err, "[8:5] Runtime Error: Awaited task can never finish.\n");
+}
+
+#[test]
+fn unobserved_failure_fails_the_script() {
+    let source = "
+async fn boom() {
+    await sleep(5)
+    throw \"lost\"
+}
+boom()
+log \"done\"
+";
+    let (out, err) = fail_with("unobserved", source, &["--virtual-clock"]);
+    assert_eq!(out, "done\n");
+    assert_eq!(err, "[4:9] Runtime Error: Uncaught exception 'lost'.\n");
+}
+
+#[test]
+fn awaited_failure_can_be_caught() {
+    let source = "
+async fn boom() {
+    await sleep(5)
+    throw \"seen\"
+}
+var t = boom()
+try { await t } catch e { log \"caught \" + e }
+log \"done\"
+";
+    assert_eq!(run_with("observed", source, &["--virtual-clock"]), "caught seen\ndone\n");
+}
+
+#[test]
+fn await_into_a_field() {
+    let source = "
+class Loader {
+    fn init() { this.data = null }
+    async fn load() {
+        this.data = await sleep(5)
+        log this.data
+        var box = {\"v\": 0}
+        box.v = await spawn(fn () 42)
+        this.data = \"loaded at \" + now()
+        return box.v
+    }
+}
+var loader = Loader()
+log await loader.load()
+log loader.data
+";
+    assert_eq!(run_with("field", source, &["--virtual-clock"]), "null\n42\nloaded at 5\n");
+}
+
+#[test]
+fn async_functions_catch_a_failed_await() {
+    let source = "
+async fn boom(delay) {
+    await sleep(delay)
+    throw \"boom \" + delay
+}
+async fn guarded() {
+    try {
+        await boom(5)
+        log \"unreachable\"
+    } catch e {
+        log \"caught \" + e
+    } finally {
+        log \"cleanup at \" + now()
+    }
+    try {
+        var x = await boom(10)
+    } finally {
+        log \"finally at \" + now()
+    }
+}
+try { await guarded() } catch e { log \"outer \" + e }
+";
+    assert_eq!(run_with("guarded", source, &["--virtual-clock"]), "caught boom 5\ncleanup at 5\nfinally at 15\nouter boom 10\n");
+}
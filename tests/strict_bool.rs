@@ -0,0 +1,53 @@
+mod common;
+
+use common::{fail_with, run, run_with};
+
+#[test]
+fn every_condition_uses_the_same_truthiness() {
+    let source = "
+if 1 { log \"if\" }
+var s = \"ab\"
+var n = 0
+while s {
+    s = null
+    n = n + 1
+}
+log n
+log !\"\"
+log !null
+log null or \"x\"
+log 1 and \"\"
+log \"\" ? \"a\" : \"b\"
+log [x for x in [\"\", \"a\", null, 0] if x]
+";
+    assert_eq!(run("truthy", source), "if\n1\ntrue\ntrue\ntrue\nfalse\nb\n[\"a\", 0]\n");
+}
+
+#[test]
+fn strict_bool_accepts_booleans() {
+    let source = "
+var go = true
+while go { go = false }
+if !go and (go or true) { log true ? \"yes\" : \"no\" }
+log [x for x in [1, 2, 3] if x > 1]
+";
+    assert_eq!(run_with("booleans", source, &["--strict-bool"]), "yes\n[2, 3]\n");
+}
+
+#[test]
+fn strict_bool_reports_the_offending_condition() {
+    let cases = [
+        ("if", "if 1 { log \"x\" }\n", "[1:3]", "Number"),
+        ("while", "var i = 1\nwhile i { i = false }\n", "[2:6]", "Number"),
+        ("bang", "log !0\n", "[1:4]", "Number"),
+        ("or", "log null or true\n", "[1:7]", "Null"),
+        ("and", "log true and 1\n", "[1:11]", "Number"),
+        ("ternary", "log 1 ? 2 : 3\n", "[1:4]", "Number"),
+        ("comprehension", "log [x for x in [0, 1] if x]\n", "[1:15]", "Number"),
+    ];
+    for (name, source, loc, found) in cases {
+        let (stdout, stderr) = fail_with(name, source, &["--strict-bool"]);
+        assert_eq!(stdout, "", "{}", name);
+        assert!(stderr.contains(&format!("{} Runtime Error: Condition must be a 'Boolean', but found '{}'.", loc, found)), "{}: {}", name, stderr);
+    }
+}
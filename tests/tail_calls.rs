@@ -0,0 +1,43 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn deep_self_recursion() {
+    let source = "
+fn count(n, total) {
+    if n == 0 { return total }
+    return count(n - 1, total + 1)
+}
+log count(1000000, 0)
+";
+    assert_eq!(run("self", source), "1000000\n");
+}
+
+#[test]
+fn deep_mutual_recursion() {
+    let source = "
+fn even(n) {
+    if n == 0 { return true }
+    return odd(n - 1)
+}
+fn odd(n) {
+    if n == 0 { return false }
+    return even(n - 1)
+}
+log even(100001)
+";
+    assert_eq!(run("mutual", source), "false\n");
+}
+
+#[test]
+fn tail_call_inside_try_is_still_caught() {
+    let source = "
+fn fail() { throw \"boom\" }
+fn attempt() {
+    try { return fail() } catch e { return \"caught \" + e }
+}
+log attempt()
+";
+    assert_eq!(run("try", source), "caught boom\n");
+}
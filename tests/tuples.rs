@@ -0,0 +1,39 @@
+mod common;
+
+use common::{fail, run};
+
+#[test]
+fn functions_return_tuples_that_index_and_destructure() {
+    let source = "
+fn pair() { return 1, \"x\" }
+var t = pair()
+log t
+log t[0]
+var [a, b] = pair()
+log a
+log b
+";
+    assert_eq!(run("pair", source), "(1, \"x\")\n1\n1\nx\n");
+}
+
+#[test]
+fn tuples_lists_and_maps_compare_by_contents() {
+    let source = "
+fn pair() { return 1, \"x\" }
+log (1, \"x\") == pair()
+log (1, 2) == (1, 3)
+log (1, 2) == (1, 2, 3)
+log [1, (2, 3)] == [1, (2, 3)]
+log [1, 2] == [1, 2, 3]
+log {\"a\": [1], \"b\": (2, 3)} == {\"b\": (2, 3), \"a\": [1]}
+log {\"a\": 1} == {\"a\": 2}
+log [1] == (1,)
+";
+    assert_eq!(run("equality", source), "true\nfalse\nfalse\ntrue\nfalse\ntrue\nfalse\nfalse\n");
+}
+
+#[test]
+fn destructuring_checks_the_length() {
+    let stderr = fail("length", "var [a, b] = (1, 2, 3)\n");
+    assert!(stderr.contains("Cannot destructure a tuple of length 3 into 2 targets."));
+}